@@ -0,0 +1,77 @@
+use image::RgbImage;
+use msdf::{
+    atlas::AtlasBuilder,
+    bmfont,
+    json::write_json,
+    metrics::{kerning_pairs, FontMetrics},
+    raster::FieldType,
+};
+use ttf_parser::Face;
+
+const SIZE: f64 = 32.0;
+const RANGE: f64 = 4.0;
+
+fn main() {
+    let font = include_bytes!("fonts/OpenSans-Regular.ttf");
+    let face = Face::from_slice(font, 0).unwrap();
+    let examples_dest = std::path::Path::new("examples/out");
+    std::fs::create_dir_all(examples_dest).unwrap();
+    println!("Writing example outputs to {}", examples_dest.display());
+
+    let scale = SIZE / face.units_per_em().unwrap_or(1024) as f64;
+    let mut builder = AtlasBuilder::new(scale, RANGE).with_padding(1);
+    for c in (' '..='~').chain("äöüß€".chars()) {
+        builder.add_char(&face, c);
+    }
+    let atlas = builder.build::<u8>().unwrap();
+    for glyph in &atlas.layout.glyphs {
+        if let (Some(c), Some(placement)) = (glyph.codepoint, &glyph.placement) {
+            println!(
+                "{:?}: {}x{} at ({}, {})",
+                c, placement.width, placement.height, placement.x, placement.y
+            );
+        }
+    }
+
+    let metrics = FontMetrics::from_face(&face);
+    let kerning = kerning_pairs(&face, &atlas.layout.glyphs);
+    let mut json = String::new();
+    write_json(
+        &mut json,
+        &atlas.layout,
+        FieldType::Msdf,
+        &metrics,
+        &kerning,
+    )
+    .unwrap();
+    std::fs::write(examples_dest.join("atlas.json"), json).unwrap();
+
+    let mut fnt = String::new();
+    bmfont::write_text(
+        &mut fnt,
+        &atlas.layout,
+        FieldType::Msdf,
+        &metrics,
+        &kerning,
+        "Open Sans",
+        "atlas.png",
+    )
+    .unwrap();
+    std::fs::write(examples_dest.join("atlas.fnt"), fnt).unwrap();
+    let fnt = bmfont::to_binary(
+        &atlas.layout,
+        FieldType::Msdf,
+        &metrics,
+        &kerning,
+        "Open Sans",
+        "atlas.png",
+    )
+    .unwrap();
+    std::fs::write(examples_dest.join("atlas_binary.fnt"), fnt).unwrap();
+
+    let (width, height) = (atlas.layout.width as u32, atlas.layout.height as u32);
+    RgbImage::from_raw(width, height, atlas.bitmap.into_vec())
+        .unwrap()
+        .save(examples_dest.join("atlas.png"))
+        .unwrap();
+}
@@ -0,0 +1,53 @@
+use image::{GrayImage, ImageBuffer, Luma, RgbImage, RgbaImage};
+use msdf::{
+    frame::Framing,
+    raster::{DistanceMapping, Rasterizer},
+    shape::Shape,
+};
+use ttf_parser::Face;
+
+fn main() {
+    const OUTLINE: u8 = 8;
+    const SCALE: f64 = 0.05;
+
+    let font = include_bytes!("fonts/OpenSans-Regular.ttf");
+    let face = Face::from_slice(font, 0).unwrap();
+    let glyph_id = face.glyph_index('A').unwrap();
+    let mut shape_builder = Shape::<f64>::builder();
+    face.outline_glyph(glyph_id, &mut shape_builder);
+    let mut shape = shape_builder.finish().unwrap();
+    shape.color_edges_simple(3.0, 0);
+
+    let frame = Framing::new(OUTLINE as f64).scaled(shape.aabr(), SCALE);
+    let (width, height) = (frame.width as u32, frame.height as u32);
+    let rasterizer = Rasterizer::new().with_frame(&frame);
+    let range = OUTLINE as f64;
+    let examples_dest = std::path::Path::new("examples/out");
+    std::fs::create_dir_all(examples_dest).unwrap();
+
+    let mut bitmap = GrayImage::new(width, height);
+    rasterizer.rasterize_bitmap(&shape, &mut bitmap);
+    bitmap.save(examples_dest.join("image_bitmap.png")).unwrap();
+
+    let mut sdf = GrayImage::new(width, height);
+    rasterizer.rasterize_sdf(&shape, range, DistanceMapping::Normalized, &mut sdf);
+    sdf.save(examples_dest.join("image_sdf.png")).unwrap();
+
+    let mut msdf = RgbImage::new(width, height);
+    rasterizer.rasterize_msdf(&shape, range, DistanceMapping::Normalized, &mut msdf);
+    msdf.save(examples_dest.join("image_msdf.png")).unwrap();
+
+    let mut mtsdf = RgbaImage::new(width, height);
+    rasterizer.rasterize_mtsdf(&shape, range, DistanceMapping::Normalized, &mut mtsdf);
+    mtsdf.save(examples_dest.join("image_mtsdf.png")).unwrap();
+
+    // distances in pixels, for custom quantization
+    let mut distances = ImageBuffer::<Luma<f32>, Vec<f32>>::new(width, height);
+    rasterizer.rasterize_sdf(&shape, range, DistanceMapping::Pixels, &mut distances);
+    let (min, max) = distances
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), d| {
+            (min.min(*d), max.max(*d))
+        });
+    println!("distances range from {} to {} pixels", min, max);
+}
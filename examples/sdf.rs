@@ -1,5 +1,11 @@
-use image::{GrayImage, Luma};
-use msdf::{raster::Rasterizer, shape::Shape};
+use image::{imageops::invert, GrayImage, RgbImage, RgbaImage};
+use msdf::{
+    bitmap::BitmapBuf,
+    correction::ErrorCorrectionConfig,
+    frame::Framing,
+    raster::{DistanceMapping, Rasterizer},
+    shape::Shape,
+};
 use ttf_parser::{Face, GlyphId};
 
 const SIZE: f64 = 100.0;
@@ -14,52 +20,125 @@ fn glyph_shape(face: &Face, glyph_id: GlyphId) -> Shape<f64> {
 fn raster_bitmap(face: &Face, c: char) -> GrayImage {
     let scale = SIZE / face.units_per_em().unwrap_or(1024) as f64;
     let glyph_id = face.glyph_index(c).unwrap();
-    let shape = glyph_shape(&face, glyph_id);
-
-    let width = ((shape.aabr().max.x - shape.aabr().min.x) * scale) as usize + 1;
-    let height = ((shape.aabr().max.y - shape.aabr().min.y) * scale) as usize + 1;
-    let mut image = GrayImage::new(width as u32, height as u32);
-    let put_pixel = |x: usize, y: usize, filled: bool| {
-        let color;
-        if filled {
-            color = Luma([0]);
-        } else {
-            color = Luma([255]);
-        }
-        image.put_pixel(x as u32, y as u32, color);
-    };
+    let shape = glyph_shape(face, glyph_id);
+
+    let frame = Framing::new(0.0).scaled(shape.aabr(), scale);
+    let mut bitmap = BitmapBuf::new(frame.width, frame.height, 1);
 
     Rasterizer::new()
-        .with_scale(scale)
-        .with_translate(-shape.aabr().min.x, -shape.aabr().min.y)
-        .rasterize_bitmap(&shape, width, height, put_pixel);
+        .with_frame(&frame)
+        .rasterize_bitmap(&shape, &mut bitmap);
 
+    let mut image =
+        GrayImage::from_raw(frame.width as u32, frame.height as u32, bitmap.into_vec()).unwrap();
+    invert(&mut image);
     image
 }
 
 fn raster_sdf(face: &Face, c: char) -> GrayImage {
     let scale = SIZE / face.units_per_em().unwrap_or(1024) as f64;
     let glyph_id = face.glyph_index(c).unwrap();
-    let shape = glyph_shape(&face, glyph_id);
+    let shape = glyph_shape(face, glyph_id);
 
-    let width = ((shape.aabr().max.x - shape.aabr().min.x) * scale) as usize + OFFSET as usize * 2;
-    let height = ((shape.aabr().max.y - shape.aabr().min.y) * scale) as usize + OFFSET as usize * 2;
-    let mut image = GrayImage::new(width as u32, height as u32);
-    let put_pixel = |x: usize, y: usize, value: u8| {
-        image.put_pixel(x as u32, y as u32, Luma([value]));
-    };
+    let frame = Framing::new(OFFSET as f64).scaled(shape.aabr(), scale);
+    let mut bitmap = BitmapBuf::new(frame.width, frame.height, 1);
 
-    Rasterizer::new()
-        .with_scale(scale)
-        .with_translate(
-            -shape.aabr().min.x + OFFSET as f64 / scale,
-            -shape.aabr().min.y + OFFSET as f64 / scale,
-        )
-        .rasterize_sdf(&shape, width, height, OFFSET, put_pixel);
+    Rasterizer::new().with_frame(&frame).rasterize_sdf(
+        &shape,
+        OFFSET as f64,
+        DistanceMapping::Normalized,
+        &mut bitmap,
+    );
+
+    GrayImage::from_raw(frame.width as u32, frame.height as u32, bitmap.into_vec()).unwrap()
+}
+
+fn raster_psdf(face: &Face, c: char) -> GrayImage {
+    let scale = SIZE / face.units_per_em().unwrap_or(1024) as f64;
+    let glyph_id = face.glyph_index(c).unwrap();
+    let shape = glyph_shape(face, glyph_id);
+
+    let frame = Framing::new(OFFSET as f64).scaled(shape.aabr(), scale);
+    let mut bitmap = BitmapBuf::new(frame.width, frame.height, 1);
+
+    Rasterizer::new().with_frame(&frame).rasterize_psdf(
+        &shape,
+        OFFSET as f64,
+        DistanceMapping::Normalized,
+        &mut bitmap,
+    );
+
+    GrayImage::from_raw(frame.width as u32, frame.height as u32, bitmap.into_vec()).unwrap()
+}
+
+fn raster_msdf(face: &Face, c: char) -> RgbImage {
+    let scale = SIZE / face.units_per_em().unwrap_or(1024) as f64;
+    let glyph_id = face.glyph_index(c).unwrap();
+    let mut shape = glyph_shape(face, glyph_id);
+    shape.color_edges_simple(3.0, 0);
+
+    let frame = Framing::new(OFFSET as f64).scaled(shape.aabr(), scale);
+    let mut bitmap = BitmapBuf::new(frame.width, frame.height, 3);
+
+    let rasterizer = Rasterizer::new().with_frame(&frame);
+    rasterizer.rasterize_msdf(
+        &shape,
+        OFFSET as f64,
+        DistanceMapping::Normalized,
+        &mut bitmap,
+    );
+    rasterizer.correct_msdf_errors(
+        &shape,
+        OFFSET as f64,
+        DistanceMapping::Normalized,
+        &mut bitmap,
+        &ErrorCorrectionConfig::default(),
+    );
+
+    RgbImage::from_raw(frame.width as u32, frame.height as u32, bitmap.into_vec()).unwrap()
+}
+
+fn render_msdf(msdf: &RgbImage, scale: u32) -> GrayImage {
+    let (width, height) = (msdf.width() as usize, msdf.height() as usize);
+    let field = BitmapBuf::from_vec(msdf.as_raw().clone(), width, height, 3);
+    let mut bitmap = BitmapBuf::new(width * scale as usize, height * scale as usize, 1);
+
+    Rasterizer::<f64>::new().render_distance_field(
+        &field,
+        OFFSET as f64,
+        DistanceMapping::Normalized,
+        &mut bitmap,
+    );
 
+    let mut image = GrayImage::from_raw(
+        width as u32 * scale,
+        height as u32 * scale,
+        bitmap.into_vec(),
+    )
+    .unwrap();
+    invert(&mut image);
     image
 }
 
+fn raster_mtsdf(face: &Face, c: char) -> RgbaImage {
+    let scale = SIZE / face.units_per_em().unwrap_or(1024) as f64;
+    let glyph_id = face.glyph_index(c).unwrap();
+    let mut shape = glyph_shape(face, glyph_id);
+    shape.color_edges_simple(3.0, 0);
+
+    let frame = Framing::new(OFFSET as f64).scaled(shape.aabr(), scale);
+    let mut bitmap = BitmapBuf::new(frame.width, frame.height, 4);
+
+    Rasterizer::new().with_frame(&frame).rasterize_mtsdf(
+        &shape,
+        OFFSET as f64,
+        DistanceMapping::Normalized,
+        &mut bitmap,
+    );
+
+    RgbaImage::from_raw(frame.width as u32, frame.height as u32, bitmap.into_vec()).unwrap()
+}
+
 fn main() {
     let font = include_bytes!("fonts/OpenSans-Regular.ttf");
     let face = Face::from_slice(font, 0).unwrap();
@@ -86,4 +165,36 @@ fn main() {
     raster_sdf(&face, 'R')
         .save(examples_dest.join("sdf_c.png"))
         .unwrap();
+
+    raster_psdf(&face, '#')
+        .save(examples_dest.join("psdf_a.png"))
+        .unwrap();
+    raster_psdf(&face, '@')
+        .save(examples_dest.join("psdf_b.png"))
+        .unwrap();
+    raster_psdf(&face, 'R')
+        .save(examples_dest.join("psdf_c.png"))
+        .unwrap();
+
+    raster_msdf(&face, '#')
+        .save(examples_dest.join("msdf_a.png"))
+        .unwrap();
+    raster_msdf(&face, '@')
+        .save(examples_dest.join("msdf_b.png"))
+        .unwrap();
+    let msdf = raster_msdf(&face, 'R');
+    msdf.save(examples_dest.join("msdf_c.png")).unwrap();
+    render_msdf(&msdf, 8)
+        .save(examples_dest.join("msdf_c_render.png"))
+        .unwrap();
+
+    raster_mtsdf(&face, '#')
+        .save(examples_dest.join("mtsdf_a.png"))
+        .unwrap();
+    raster_mtsdf(&face, '@')
+        .save(examples_dest.join("mtsdf_b.png"))
+        .unwrap();
+    raster_mtsdf(&face, 'R')
+        .save(examples_dest.join("mtsdf_c.png"))
+        .unwrap();
 }
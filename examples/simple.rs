@@ -1,5 +1,10 @@
-use image::{GrayImage, Luma};
-use msdf::{raster::Rasterizer, shape::Shape};
+use image::{imageops::invert, GrayImage};
+use msdf::{
+    bitmap::BitmapBuf,
+    frame::Framing,
+    raster::{DistanceMapping, Rasterizer},
+    shape::Shape,
+};
 use ttf_parser::Face;
 
 fn main() {
@@ -15,33 +20,27 @@ fn main() {
     face.outline_glyph(glyph_id, &mut shape_builder);
     let shape = shape_builder.finish().unwrap();
 
-    // create a target image
-    let width = ((shape.aabr().max.x - shape.aabr().min.x) * SCALE) as usize + 2 * OUTLINE as usize;
-    let height =
-        ((shape.aabr().max.y - shape.aabr().min.y) * SCALE) as usize + 2 * OUTLINE as usize;
-    let mut image = GrayImage::new(width as u32, height as u32);
+    // fit the shape and the outline into a target bitmap
+    let frame = Framing::new(OUTLINE as f64).scaled(shape.aabr(), SCALE);
+    let (width, height) = (frame.width, frame.height);
+    let mut bitmap = BitmapBuf::new(width, height, 1);
 
-    let rasterizer = Rasterizer::new().with_scale(SCALE).with_translate(
-        -shape.aabr().min.x + OUTLINE as f64 / SCALE,
-        -shape.aabr().min.y + OUTLINE as f64 / SCALE,
-    );
+    let rasterizer = Rasterizer::new().with_frame(&frame);
 
     // rasterize shape to image as bitmap
-    rasterizer.rasterize_bitmap(&shape, width, height, |x: usize, y: usize, value: bool| {
-        let value = if value { 0 } else { 255 };
-        image.put_pixel(x as u32, y as u32, Luma([value]));
-    });
+    rasterizer.rasterize_bitmap(&shape, &mut bitmap);
+    let mut image =
+        GrayImage::from_raw(width as u32, height as u32, bitmap.as_slice().to_vec()).unwrap();
+    invert(&mut image);
     image.save("examples/out/simple_bitmap.png").unwrap();
 
     // rasterize shape to the image as SDF
     rasterizer.rasterize_sdf(
         &shape,
-        width,
-        height,
-        OUTLINE,
-        |x: usize, y: usize, value: u8| {
-            image.put_pixel(x as u32, y as u32, Luma([value]));
-        },
+        OUTLINE as f64,
+        DistanceMapping::Normalized,
+        &mut bitmap,
     );
+    let image = GrayImage::from_raw(width as u32, height as u32, bitmap.into_vec()).unwrap();
     image.save("examples/out/simple_sdf.png").unwrap();
 }
@@ -0,0 +1,660 @@
+//! Packing and rasterization of multiple shapes into a single atlas.
+//!
+//! Every shape gets a cell, which covers its bounding box plus the distance
+//! range on each side. The cells are packed with a skyline packer, and all
+//! shapes are rasterized into their cells of one shared `Bitmap`.
+
+use alloc::vec::Vec;
+use approx::RelativeEq;
+use num_traits::{real::Real, FloatConst};
+use vek::Aabr;
+
+#[cfg(feature = "parallel")]
+use crate::bitmap::split_sections;
+use crate::{
+    bitmap::{Bitmap, BitmapBuf, BitmapView, Channel},
+    correction::{ErrorCorrectionConfig, ErrorCorrectionMode},
+    frame::{Frame, Framing},
+    raster::{DistanceMapping, FieldType, MaybeSync, Rasterizer},
+    shape::{ColoringStrategy, FillRule, Shape},
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct SkylineNode {
+    x: usize,
+    y: usize,
+    width: usize,
+}
+
+/// Packs rectangles into a fixed area by tracking the upper edge (the
+/// *skyline*) of the already placed rectangles.
+///
+/// Rectangles are placed at the position, where their bottom edge is the
+/// highest (with `y` pointing downwards).
+#[derive(Clone, Debug)]
+pub struct SkylinePacker {
+    width: usize,
+    height: usize,
+    skyline: Vec<SkylineNode>,
+}
+
+impl SkylinePacker {
+    pub fn new(width: usize, height: usize) -> Self {
+        SkylinePacker {
+            width,
+            height,
+            skyline: alloc::vec![SkylineNode { x: 0, y: 0, width }],
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the `y` position of a rectangle starting at the node `index`,
+    /// if it fits.
+    fn fit(&self, index: usize, width: usize, height: usize) -> Option<usize> {
+        let x = self.skyline[index].x;
+        if x + width > self.width {
+            return None;
+        }
+        let mut y = 0;
+        for node in &self.skyline[index..] {
+            if node.x >= x + width {
+                break;
+            }
+            y = y.max(node.y);
+        }
+        if y + height > self.height {
+            None
+        } else {
+            Some(y)
+        }
+    }
+
+    /// Reserves a rectangle of the given size and returns the position of its
+    /// top-left corner, or `None` if there is no space left.
+    pub fn pack(&mut self, width: usize, height: usize) -> Option<(usize, usize)> {
+        let mut best: Option<(usize, usize, usize)> = None;
+        for index in 0..self.skyline.len() {
+            if let Some(y) = self.fit(index, width, height) {
+                let x = self.skyline[index].x;
+                let better = match best {
+                    Some((_, best_x, best_y)) => (y, x) < (best_y, best_x),
+                    None => true,
+                };
+                if better {
+                    best = Some((index, x, y));
+                }
+            }
+        }
+        let (index, x, y) = best?;
+        if width == 0 || height == 0 {
+            return Some((x, y));
+        }
+
+        self.skyline.insert(
+            index,
+            SkylineNode {
+                x,
+                y: y + height,
+                width,
+            },
+        );
+        // shrink or remove the nodes covered by the new node
+        let end = x + width;
+        let next = index + 1;
+        while next < self.skyline.len() {
+            let node = &mut self.skyline[next];
+            if node.x >= end {
+                break;
+            }
+            if node.x + node.width <= end {
+                self.skyline.remove(next);
+            } else {
+                node.width -= end - node.x;
+                node.x = end;
+                break;
+            }
+        }
+        // merge neighbors of the same height
+        let mut i = 1;
+        while i < self.skyline.len() {
+            if self.skyline[i - 1].y == self.skyline[i].y {
+                self.skyline[i - 1].width += self.skyline[i].width;
+                self.skyline.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        Some((x, y))
+    }
+}
+
+/// The cell of a glyph in the atlas.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Placement<S> {
+    /// The left position of the cell in pixels.
+    pub x: usize,
+    /// The top position of the cell in pixels.
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    /// The area (in shape units), which is covered by the cell.
+    pub bounds: Aabr<S>,
+}
+
+/// A glyph (or shape) of an atlas.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasGlyph<S> {
+    /// The id of the glyph in the font, if it was added by `add_glyph`.
+    pub glyph_id: Option<u16>,
+    /// The character, if it was added by `add_char`.
+    pub codepoint: Option<char>,
+    /// The horizontal advance (in shape units).
+    pub advance: S,
+    /// The cell in the atlas, or `None` for empty glyphs (like spaces).
+    pub placement: Option<Placement<S>>,
+}
+
+/// The size of an atlas and the placement of all glyphs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasLayout<S> {
+    pub width: usize,
+    pub height: usize,
+    /// The scale from shape units to pixels.
+    pub scale: S,
+    /// The distance range in pixels.
+    pub range: S,
+    /// The glyphs in the order they were added.
+    pub glyphs: Vec<AtlasGlyph<S>>,
+}
+
+/// A rasterized atlas.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Atlas<S, T> {
+    pub layout: AtlasLayout<S>,
+    pub bitmap: BitmapBuf<T>,
+}
+
+/// Collects shapes and builds an atlas of them.
+pub struct AtlasBuilder<S> {
+    scale: S,
+    range: S,
+    padding: usize,
+    size: Option<(usize, usize)>,
+    field_type: FieldType,
+    mapping: DistanceMapping,
+    coloring: ColoringStrategy,
+    angle_threshold: S,
+    fill_rule: FillRule,
+    overlap_support: bool,
+    preprocess: bool,
+    normalize: bool,
+    correction: ErrorCorrectionConfig<S>,
+    glyphs: Vec<AtlasGlyph<S>>,
+    shapes: Vec<Option<Shape<S>>>,
+}
+
+impl<S> AtlasBuilder<S>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    /// Creates an empty atlas, where shapes are scaled by `scale` (pixels per
+    /// shape unit) and distances are rasterized within `range` pixels.
+    ///
+    /// For fonts, `scale` is usually the size of the em in pixels divided by
+    /// the units per em.
+    pub fn new(scale: S, range: S) -> Self {
+        AtlasBuilder {
+            scale,
+            range,
+            padding: 0,
+            size: None,
+            field_type: FieldType::Msdf,
+            mapping: DistanceMapping::Normalized,
+            coloring: ColoringStrategy::default(),
+            angle_threshold: From::from(3),
+            fill_rule: FillRule::default(),
+            overlap_support: false,
+            preprocess: false,
+            normalize: false,
+            correction: ErrorCorrectionConfig::default(),
+            glyphs: Vec::new(),
+            shapes: Vec::new(),
+        }
+    }
+
+    /// Sets the number of empty pixels between two cells, and between the
+    /// cells and the border of the atlas.
+    pub fn with_padding(mut self, padding: usize) -> Self {
+        self.padding = padding;
+        self
+    }
+    /// Uses a fixed size for the atlas instead of the smallest power of two.
+    pub fn with_size(mut self, width: usize, height: usize) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+    pub fn with_field_type(mut self, field_type: FieldType) -> Self {
+        self.field_type = field_type;
+        self
+    }
+    pub fn with_mapping(mut self, mapping: DistanceMapping) -> Self {
+        self.mapping = mapping;
+        self
+    }
+    /// Sets how the edges of multi-channel distance fields are colored (see
+    /// `Shape::color_edges`).
+    pub fn with_coloring(mut self, strategy: ColoringStrategy, angle_threshold: S) -> Self {
+        self.coloring = strategy;
+        self.angle_threshold = angle_threshold;
+        self
+    }
+    /// Sets the fill rule of all shapes (see `Rasterizer::with_fill_rule`).
+    pub fn with_fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_rule = fill_rule;
+        self
+    }
+    /// Enables the combination of the distances of overlapping contours (see
+    /// `Rasterizer::with_overlap_support`).
+    pub fn with_overlap_support(mut self, overlap_support: bool) -> Self {
+        self.overlap_support = overlap_support;
+        self
+    }
+    /// Resolves the overlapping contours of the shapes before they are
+    /// rendered (see `Shape::resolve_overlaps`).
+    pub fn with_preprocessing(mut self, preprocess: bool) -> Self {
+        self.preprocess = preprocess;
+        self
+    }
+    /// Orients the contours of the shapes consistently before they are
+    /// rendered (see `Shape::normalize`).
+    pub fn with_normalization(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+    /// Sets the error correction of multi-channel distance fields.
+    pub fn with_error_correction(mut self, config: ErrorCorrectionConfig<S>) -> Self {
+        self.correction = config;
+        self
+    }
+
+    #[inline]
+    pub fn field_type(&self) -> FieldType {
+        self.field_type
+    }
+
+    #[inline]
+    pub fn glyphs(&self) -> &[AtlasGlyph<S>] {
+        &self.glyphs
+    }
+
+    /// Adds a shape with the given horizontal `advance` (in shape units) and
+    /// returns its index.
+    pub fn add_shape(&mut self, shape: Option<Shape<S>>, advance: S) -> usize {
+        self.glyphs.push(AtlasGlyph {
+            glyph_id: None,
+            codepoint: None,
+            advance,
+            placement: None,
+        });
+        self.shapes.push(shape);
+        self.glyphs.len() - 1
+    }
+
+    /// Returns the cell of `shape`, which covers its bounding box plus the
+    /// distance range.
+    fn cell(&self, shape: &Shape<S>) -> Frame<S> {
+        Framing::new(self.range).scaled(shape.aabr(), self.scale)
+    }
+
+    /// Tries to pack all cells into an atlas of the given size.
+    ///
+    /// Every cell reserves the padding on its right and bottom side, so the
+    /// packed area is inset by the padding at the top and left border.
+    fn pack(&self, width: usize, height: usize) -> Option<Vec<AtlasGlyph<S>>> {
+        let padding = self.padding;
+        let mut packer = SkylinePacker::new(
+            width.saturating_sub(padding),
+            height.saturating_sub(padding),
+        );
+        let mut glyphs = self.glyphs.clone();
+        let mut cells: Vec<(usize, Frame<S>)> = self
+            .shapes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, shape)| shape.as_ref().map(|shape| (i, self.cell(shape))))
+            .collect();
+        // taller cells first
+        cells.sort_by_key(|(_, cell)| core::cmp::Reverse(cell.height));
+        for (i, cell) in cells {
+            let (x, y) = packer.pack(cell.width + padding, cell.height + padding)?;
+            glyphs[i].placement = Some(Placement {
+                x: x + padding,
+                y: y + padding,
+                width: cell.width,
+                height: cell.height,
+                bounds: cell.bounds(),
+            });
+        }
+        Some(glyphs)
+    }
+
+    /// Computes the size of the atlas and the placement of all glyphs.
+    ///
+    /// Without a fixed size (see `with_size`), the smallest atlas with power
+    /// of two dimensions is chosen. Returns `None`, if the glyphs don't fit
+    /// into the fixed size.
+    pub fn layout(&self) -> Option<AtlasLayout<S>> {
+        let (width, height, glyphs) = if let Some((width, height)) = self.size {
+            (width, height, self.pack(width, height)?)
+        } else {
+            let mut area = 0;
+            let mut min_width = 1;
+            let mut min_height = 1;
+            for shape in self.shapes.iter().flatten() {
+                let cell = self.cell(shape);
+                let (width, height) = (cell.width + self.padding, cell.height + self.padding);
+                area += width * height;
+                // plus the padding at the top and left border
+                min_width = min_width.max(width + self.padding);
+                min_height = min_height.max(height + self.padding);
+            }
+            let mut width = min_width.next_power_of_two();
+            let mut height = min_height.next_power_of_two();
+            while width * height < area {
+                if width <= height {
+                    width *= 2;
+                } else {
+                    height *= 2;
+                }
+            }
+            loop {
+                if let Some(glyphs) = self.pack(width, height) {
+                    break (width, height, glyphs);
+                }
+                if width <= height {
+                    width *= 2;
+                } else {
+                    height *= 2;
+                }
+            }
+        };
+        Some(AtlasLayout {
+            width,
+            height,
+            scale: self.scale,
+            range: self.range,
+            glyphs,
+        })
+    }
+
+    /// Rasterizes `shape` into its cell `target`.
+    fn render_glyph<B: Bitmap>(&self, placement: &Placement<S>, shape: &Shape<S>, target: &mut B)
+    where
+        S: MaybeSync,
+    {
+        let rasterizer = Rasterizer::new()
+            .with_scale(self.scale)
+            .with_translate(-placement.bounds.min.x, -placement.bounds.min.y)
+            .with_fill_rule(self.fill_rule)
+            .with_overlap_support(self.overlap_support);
+        let normalized = if self.normalize {
+            let mut shape = shape.clone();
+            shape.normalize();
+            Some(shape)
+        } else {
+            None
+        };
+        let shape = normalized.as_ref().unwrap_or(shape);
+        let resolved = if self.preprocess {
+            shape.resolve_overlaps(self.fill_rule)
+        } else {
+            None
+        };
+        let shape = resolved.as_ref().unwrap_or(shape);
+        if self.field_type.is_multi_channel() {
+            let mut shape = shape.clone();
+            shape.color_edges(self.coloring, self.angle_threshold, 0);
+            rasterizer.rasterize(self.field_type, &shape, self.range, self.mapping, target);
+            if self.correction.mode != ErrorCorrectionMode::Disabled {
+                rasterizer.correct_msdf_errors(
+                    &shape,
+                    self.range,
+                    self.mapping,
+                    target,
+                    &self.correction,
+                );
+            }
+        } else {
+            rasterizer.rasterize(self.field_type, shape, self.range, self.mapping, target);
+        }
+    }
+
+    /// Rasterizes all glyphs into their cells of `target`, which has to match
+    /// the size of `layout` and the channels of the field type.
+    ///
+    /// With the "parallel" feature, the glyphs are rendered concurrently, if
+    /// `target` exposes its storage by `Bitmap::rows_mut`.
+    pub fn render_into<B: Bitmap>(&self, layout: &AtlasLayout<S>, target: &mut B)
+    where
+        S: MaybeSync,
+    {
+        assert_eq!(
+            (target.width(), target.height()),
+            (layout.width, layout.height),
+            "unexpected atlas size"
+        );
+        let glyphs = layout
+            .glyphs
+            .iter()
+            .zip(&self.shapes)
+            .filter_map(|(glyph, shape)| match (&glyph.placement, shape) {
+                (Some(placement), Some(shape)) => Some((placement, shape)),
+                _ => None,
+            });
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            let channels = target.channels();
+            match target.rows_mut() {
+                Some((data, stride)) if stride > 0 => {
+                    let glyphs: Vec<_> = glyphs.collect();
+                    let sections: Vec<_> = glyphs
+                        .iter()
+                        .map(|(p, _)| (p.x, p.y, p.width, p.height))
+                        .collect();
+                    let mut cells = split_sections(data, stride, channels, &sections);
+                    glyphs.par_iter().zip(cells.par_iter_mut()).for_each(
+                        |(&(placement, shape), cell)| self.render_glyph(placement, shape, cell),
+                    );
+                    return;
+                }
+                _ => {}
+            }
+        }
+        for (placement, shape) in glyphs {
+            let mut view = BitmapView::new(
+                target,
+                placement.x,
+                placement.y,
+                placement.width,
+                placement.height,
+            );
+            self.render_glyph(placement, shape, &mut view);
+        }
+    }
+
+    /// Lays out and rasterizes all glyphs into a new bitmap.
+    pub fn build<T: Channel + Default>(&self) -> Option<Atlas<S, T>>
+    where
+        S: MaybeSync,
+    {
+        let layout = self.layout()?;
+        let mut bitmap = BitmapBuf::new(layout.width, layout.height, self.field_type.channels());
+        self.render_into(&layout, &mut bitmap);
+        Some(Atlas { layout, bitmap })
+    }
+}
+
+#[cfg(feature = "ttf-parser")]
+impl<S> AtlasBuilder<S>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+    crate::shape::ShapeBuilder<S>: ttf_parser::OutlineBuilder,
+{
+    /// Adds the outline of a glyph of `face` and returns its index.
+    pub fn add_glyph(&mut self, face: &ttf_parser::Face, glyph_id: ttf_parser::GlyphId) -> usize {
+        let mut builder = Shape::builder();
+        let shape = face
+            .outline_glyph(glyph_id, &mut builder)
+            .and_then(|_| builder.finish());
+        let advance = face
+            .glyph_hor_advance(glyph_id)
+            .map_or(S::zero(), From::from);
+        let index = self.add_shape(shape, advance);
+        self.glyphs[index].glyph_id = Some(glyph_id.0);
+        index
+    }
+
+    /// Adds the glyph of the character `c` and returns its index, or `None`
+    /// if `face` has no glyph for `c`.
+    pub fn add_char(&mut self, face: &ttf_parser::Face, c: char) -> Option<usize> {
+        let glyph_id = face.glyph_index(c)?;
+        let index = self.add_glyph(face, glyph_id);
+        self.glyphs[index].codepoint = Some(c);
+        Some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap::RowByRow;
+    use crate::svg::parse_path;
+
+    /// A rectangle as `(x, y, width, height)`.
+    type Rect = (usize, usize, usize, usize);
+
+    /// Returns `true`, if there are at least `gap` pixels between `a` and `b`.
+    fn separated(a: Rect, b: Rect, gap: usize) -> bool {
+        a.0 + a.2 + gap <= b.0
+            || b.0 + b.2 + gap <= a.0
+            || a.1 + a.3 + gap <= b.1
+            || b.1 + b.3 + gap <= a.1
+    }
+
+    /// Rectangle sizes from `1` to `max`, in a fixed pseudo-random order.
+    fn sizes(count: usize, max: usize) -> Vec<(usize, usize)> {
+        let mut state = 12345u32;
+        let mut next = move || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            1 + (state >> 16) as usize % max
+        };
+        (0..count).map(|_| (next(), next())).collect()
+    }
+
+    fn rect_shape(width: f64, height: f64) -> Shape<f64> {
+        let path = alloc::format!("M 0 0 L 0 {h} L {w} {h} L {w} 0 Z", w = width, h = height);
+        parse_path(&path).unwrap()
+    }
+
+    fn placements(layout: &AtlasLayout<f64>) -> Vec<Rect> {
+        let placements = layout
+            .glyphs
+            .iter()
+            .filter_map(|glyph| glyph.placement.as_ref());
+        placements.map(|p| (p.x, p.y, p.width, p.height)).collect()
+    }
+
+    #[test]
+    fn packed_rectangles_dont_overlap() {
+        let mut packer = SkylinePacker::new(64, 48);
+        let mut packed: Vec<Rect> = Vec::new();
+        let mut rejected = 0;
+        for (width, height) in sizes(200, 12) {
+            match packer.pack(width, height) {
+                Some((x, y)) => {
+                    assert!(x + width <= 64 && y + height <= 48);
+                    for &other in &packed {
+                        assert!(separated((x, y, width, height), other, 0));
+                    }
+                    packed.push((x, y, width, height));
+                }
+                None => rejected += 1,
+            }
+        }
+        assert!(packed.len() > 20 && rejected > 0);
+        assert_eq!(packer.pack(65, 1), None);
+        assert_eq!(packer.pack(1, 49), None);
+    }
+
+    #[test]
+    fn cells_are_padded() {
+        for &padding in &[0, 1, 3] {
+            let mut builder = AtlasBuilder::new(1.0, 1.0).with_padding(padding);
+            for (width, height) in sizes(40, 10) {
+                builder.add_shape(Some(rect_shape(width as f64, height as f64)), 1.0);
+            }
+            let layout = builder.layout().unwrap();
+            let cells = placements(&layout);
+            assert_eq!(cells.len(), 40);
+            for (i, &cell) in cells.iter().enumerate() {
+                let (x, y, width, height) = cell;
+                assert!(x >= padding && y >= padding, "{:?}", cell);
+                assert!(x + width + padding <= layout.width, "{:?}", cell);
+                assert!(y + height + padding <= layout.height, "{:?}", cell);
+                for &other in &cells[..i] {
+                    assert!(separated(cell, other, padding), "{:?} {:?}", cell, other);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn atlas_grows_for_large_glyphs() {
+        let mut builder = AtlasBuilder::new(1.0, 2.0).with_padding(2);
+        builder.add_shape(Some(rect_shape(3.0, 3.0)), 1.0);
+        builder.add_shape(Some(rect_shape(200.0, 20.0)), 1.0);
+        builder.add_shape(None, 1.0);
+        builder.add_shape(Some(rect_shape(10.0, 90.0)), 1.0);
+        let layout = builder.layout().unwrap();
+        assert!(layout.width.is_power_of_two() && layout.height.is_power_of_two());
+        assert_eq!((layout.width, layout.height), (256, 128));
+        assert!(layout.glyphs[2].placement.is_none());
+        for (x, y, width, height) in placements(&layout) {
+            assert!(x + width + 2 <= layout.width && y + height + 2 <= layout.height);
+        }
+
+        // a fixed size isn't grown
+        let builder = builder.with_size(128, 128);
+        assert_eq!(builder.layout(), None);
+    }
+
+    #[test]
+    fn concurrent_glyphs_match_the_glyph_loop() {
+        let mut builder = AtlasBuilder::new(1.5, 2.0).with_padding(1);
+        for i in 0..12 {
+            let size = 4.0 + i as f64;
+            let path = alloc::format!(
+                "M 0 0 L {s} 0 Q {s} {s} {h} {s} C 0 {s} {h} {h} 0 0 Z",
+                s = size,
+                h = size / 2.0
+            );
+            builder.add_shape(Some(parse_path(&path).unwrap()), size);
+        }
+        builder.add_shape(None, 3.0);
+        let atlas = builder.build::<f32>().unwrap();
+        let layout = &atlas.layout;
+        let mut expected = RowByRow(BitmapBuf::new(layout.width, layout.height, 3));
+        builder.render_into(layout, &mut expected);
+        assert_eq!(atlas.bitmap, expected.0);
+    }
+}
@@ -0,0 +1,269 @@
+//! The `atlas` subcommand, which generates an atlas of many glyphs, similar
+//! to msdf-atlas-gen.
+
+use std::{fs, path::Path};
+
+use image::{GrayImage, RgbImage, RgbaImage};
+use msdf::{
+    atlas::AtlasBuilder,
+    bmfont,
+    json::write_json,
+    metrics::{kerning_pairs, FontMetrics},
+    raster::FieldType,
+    shape::{ColoringStrategy, FillRule},
+};
+use ttf_parser::{name_id, Face, GlyphId};
+
+use crate::{charset::parse_charset, parse, parse_coloring, parse_field_type, parse_fill_rule};
+
+const USAGE: &str = "\
+Usage: msdf atlas -font <file> [options]
+
+Charset (default: the printable ASCII characters):
+  -charset <file>          A charset file in the format of msdf-atlas-gen,
+                           e.g. [0x20, 0x7e], 'A', \"abc\", 0x20ac
+  -chars <charset>         A charset in the same format.
+  -allglyphs               All glyphs of the font.
+
+Options:
+  -type <bitmap|sdf|psdf|msdf|mtsdf>
+                           The type of the atlas (default: msdf).
+  -size <size>             Size of the em in pixels (default: 32).
+  -pxrange <range>         Distance range in pixels (default: 2).
+  -padding <pixels>        Empty space around glyphs (default: 0).
+  -dimensions <width> <height>
+                           Fixed size of the atlas (default: the smallest
+                           power of two).
+  -coloringstrategy <simple|inktrap|distance>
+  -angle <angle>           Corner angle threshold in radians (default: 3).
+  -fillrule <nonzero|evenodd|positive>
+                           The fill rule (default: nonzero).
+  -overlap                 Combines the distances of overlapping contours.
+  -normalize               Orients the outer contours clockwise and the holes
+                           counter-clockwise.
+  -preprocess              Resolves overlapping contours and self-intersections
+                           of the glyphs.
+  -imageout <file>         The atlas image (default: atlas.png).
+  -json <file>             Metadata in the JSON format of msdf-atlas-gen.
+  -fnt <file>              Metadata in the BMFont text format.
+  -binfnt <file>           Metadata in the BMFont binary format.
+  -help                    Prints this help.
+";
+
+enum Charset {
+    Spec(String),
+    File(String),
+    AllGlyphs,
+}
+
+pub struct Options {
+    font: Option<String>,
+    charset: Charset,
+    field_type: FieldType,
+    size: f64,
+    range: f64,
+    padding: usize,
+    dimensions: Option<(usize, usize)>,
+    coloring: ColoringStrategy,
+    angle: f64,
+    fill_rule: FillRule,
+    overlap: bool,
+    preprocess: bool,
+    normalize: bool,
+    image_out: String,
+    json: Option<String>,
+    fnt: Option<String>,
+    bin_fnt: Option<String>,
+}
+
+pub fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        font: None,
+        charset: Charset::Spec("[0x20, 0x7e]".into()),
+        field_type: FieldType::Msdf,
+        size: 32.0,
+        range: 2.0,
+        padding: 0,
+        dimensions: None,
+        coloring: ColoringStrategy::Simple,
+        angle: 3.0,
+        fill_rule: FillRule::NonZero,
+        overlap: false,
+        preprocess: false,
+        normalize: false,
+        image_out: "atlas.png".into(),
+        json: None,
+        fnt: None,
+        bin_fnt: None,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-font" => options.font = Some(parse(args.next(), "-font")?),
+            "-charset" => options.charset = Charset::File(parse(args.next(), "-charset")?),
+            "-chars" => options.charset = Charset::Spec(parse(args.next(), "-chars")?),
+            "-allglyphs" => options.charset = Charset::AllGlyphs,
+            "-type" => {
+                options.field_type = parse_field_type(&parse::<String>(args.next(), "-type")?)?
+            }
+            "-size" => options.size = parse(args.next(), "-size")?,
+            "-pxrange" => options.range = parse(args.next(), "-pxrange")?,
+            "-padding" => options.padding = parse(args.next(), "-padding")?,
+            "-dimensions" => {
+                options.dimensions = Some((
+                    parse(args.next(), "-dimensions")?,
+                    parse(args.next(), "-dimensions")?,
+                ))
+            }
+            "-coloringstrategy" => {
+                options.coloring =
+                    parse_coloring(&parse::<String>(args.next(), "-coloringstrategy")?)?
+            }
+            "-angle" => options.angle = parse(args.next(), "-angle")?,
+            "-fillrule" => {
+                options.fill_rule = parse_fill_rule(&parse::<String>(args.next(), "-fillrule")?)?
+            }
+            "-overlap" => options.overlap = true,
+            "-preprocess" => options.preprocess = true,
+            "-normalize" => options.normalize = true,
+            "-imageout" => options.image_out = parse(args.next(), "-imageout")?,
+            "-json" => options.json = Some(parse(args.next(), "-json")?),
+            "-fnt" => options.fnt = Some(parse(args.next(), "-fnt")?),
+            "-binfnt" => options.bin_fnt = Some(parse(args.next(), "-binfnt")?),
+            "-help" | "--help" | "-h" => {
+                print!("{}", USAGE);
+                std::process::exit(0);
+            }
+            arg => return Err(format!("unknown argument `{}`", arg)),
+        }
+    }
+    if options.size <= 0.0 || !options.size.is_finite() {
+        return Err("the size has to be positive".into());
+    }
+    if options.range <= 0.0 || !options.range.is_finite() {
+        return Err("the range has to be positive".into());
+    }
+    if let Some((width, height)) = options.dimensions {
+        if width == 0 || height == 0 {
+            return Err("the dimensions have to be positive".into());
+        }
+    }
+    Ok(options)
+}
+
+/// Returns the family name of `face`, or the file name of the font.
+fn face_name(face: &Face, file: &str) -> String {
+    face.names()
+        .filter(|name| name.name_id() == name_id::FAMILY)
+        .find_map(|name| name.to_string())
+        .unwrap_or_else(|| {
+            Path::new(file)
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+        })
+}
+
+pub fn run(options: Options) -> Result<(), String> {
+    let file = options.font.as_deref().ok_or("missing input (-font)")?;
+    let data = fs::read(file).map_err(|e| format!("{}: {}", file, e))?;
+    let face = Face::from_slice(&data, 0).map_err(|e| format!("{}: {}", file, e))?;
+
+    let metrics = FontMetrics::<f64>::from_face(&face);
+    let mut builder = AtlasBuilder::new(options.size / metrics.em_size, options.range)
+        .with_padding(options.padding)
+        .with_field_type(options.field_type)
+        .with_coloring(options.coloring, options.angle)
+        .with_fill_rule(options.fill_rule)
+        .with_overlap_support(options.overlap)
+        .with_preprocessing(options.preprocess)
+        .with_normalization(options.normalize);
+    if let Some((width, height)) = options.dimensions {
+        builder = builder.with_size(width, height);
+    }
+
+    let charset = match &options.charset {
+        Charset::AllGlyphs => None,
+        Charset::Spec(spec) => Some(parse_charset(spec)?),
+        Charset::File(charset_file) => {
+            let spec =
+                fs::read_to_string(charset_file).map_err(|e| format!("{}: {}", charset_file, e))?;
+            Some(parse_charset(&spec).map_err(|e| format!("{}: {}", charset_file, e))?)
+        }
+    };
+    match charset {
+        None => {
+            for glyph_id in 0..face.number_of_glyphs() {
+                builder.add_glyph(&face, GlyphId(glyph_id));
+            }
+        }
+        Some(charset) => {
+            for c in charset {
+                if builder.add_char(&face, c).is_none() {
+                    eprintln!("msdf: the font has no glyph for {:?}", c);
+                }
+            }
+        }
+    }
+
+    let atlas = builder
+        .build::<u8>()
+        .ok_or("the glyphs don't fit into the atlas")?;
+    let (width, height) = (atlas.layout.width as u32, atlas.layout.height as u32);
+    let pixels = atlas.bitmap.into_vec();
+    let result = match options.field_type.channels() {
+        1 => GrayImage::from_raw(width, height, pixels)
+            .unwrap()
+            .save(&options.image_out),
+        3 => RgbImage::from_raw(width, height, pixels)
+            .unwrap()
+            .save(&options.image_out),
+        _ => RgbaImage::from_raw(width, height, pixels)
+            .unwrap()
+            .save(&options.image_out),
+    };
+    result.map_err(|e| format!("{}: {}", options.image_out, e))?;
+
+    let kerning = kerning_pairs(&face, &atlas.layout.glyphs);
+    let name = face_name(&face, file);
+    let page = Path::new(&options.image_out)
+        .file_name()
+        .map_or_else(String::new, |page| page.to_string_lossy().into_owned());
+    if let Some(json_file) = &options.json {
+        let mut json = String::new();
+        write_json(
+            &mut json,
+            &atlas.layout,
+            options.field_type,
+            &metrics,
+            &kerning,
+        )
+        .map_err(|e| e.to_string())?;
+        fs::write(json_file, json).map_err(|e| format!("{}: {}", json_file, e))?;
+    }
+    if let Some(fnt_file) = &options.fnt {
+        let mut fnt = String::new();
+        bmfont::write_text(
+            &mut fnt,
+            &atlas.layout,
+            options.field_type,
+            &metrics,
+            &kerning,
+            &name,
+            &page,
+        )
+        .map_err(|e| e.to_string())?;
+        fs::write(fnt_file, fnt).map_err(|e| format!("{}: {}", fnt_file, e))?;
+    }
+    if let Some(fnt_file) = &options.bin_fnt {
+        let fnt = bmfont::to_binary(
+            &atlas.layout,
+            options.field_type,
+            &metrics,
+            &kerning,
+            &name,
+            &page,
+        )
+        .map_err(|e| e.to_string())?;
+        fs::write(fnt_file, fnt).map_err(|e| format!("{}: {}", fnt_file, e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,187 @@
+//! Parsing of charset specifications, in the format of msdf-atlas-gen.
+//!
+//! A charset is a list of characters (`'A'`), strings (`"abc"`), codepoints
+//! (`65` or `0x41`) and inclusive ranges of these (`[0x20, 0x7e]`), separated
+//! by commas or whitespace.
+
+use std::collections::BTreeSet;
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_separators(&mut self) {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c.is_whitespace() || c == ',' {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_separators();
+        match self.chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            Some((offset, c)) => Err(format!(
+                "expected `{}` at {}, found `{}`",
+                expected, offset, c
+            )),
+            None => Err(format!("expected `{}`, found the end", expected)),
+        }
+    }
+
+    /// Reads a character of a quoted literal, resolving escapes.
+    fn literal_char(&mut self) -> Result<char, String> {
+        match self.chars.next() {
+            Some((_, '\\')) => match self.chars.next() {
+                Some((_, 'n')) => Ok('\n'),
+                Some((_, 't')) => Ok('\t'),
+                Some((_, 'r')) => Ok('\r'),
+                Some((_, '0')) => Ok('\0'),
+                Some((_, c)) => Ok(c),
+                None => Err("unterminated escape".into()),
+            },
+            Some((_, c)) => Ok(c),
+            None => Err("unterminated literal".into()),
+        }
+    }
+
+    /// Reads a single codepoint (a character literal or a number).
+    fn codepoint(&mut self) -> Result<char, String> {
+        self.skip_separators();
+        match self.chars.peek().copied() {
+            Some((_, '\'')) => {
+                self.chars.next();
+                let c = self.literal_char()?;
+                self.expect_raw('\'')?;
+                Ok(c)
+            }
+            Some((offset, c)) if c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(&(_, c)) = self.chars.peek() {
+                    if c.is_ascii_alphanumeric() {
+                        number.push(c);
+                        self.chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let code = match number
+                    .strip_prefix("0x")
+                    .or_else(|| number.strip_prefix("0X"))
+                {
+                    Some(hex) => u32::from_str_radix(hex, 16),
+                    None => number.parse(),
+                };
+                code.ok()
+                    .and_then(std::char::from_u32)
+                    .ok_or_else(|| format!("invalid codepoint `{}` at {}", number, offset))
+            }
+            Some((offset, c)) => Err(format!("unexpected `{}` at {}", c, offset)),
+            None => Err("unexpected end".into()),
+        }
+    }
+
+    fn expect_raw(&mut self, expected: char) -> Result<(), String> {
+        match self.chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            _ => Err(format!("expected `{}`", expected)),
+        }
+    }
+}
+
+/// Parses the charset `spec` into a sorted set of characters.
+pub fn parse_charset(spec: &str) -> Result<BTreeSet<char>, String> {
+    let mut parser = Parser {
+        chars: spec.char_indices().peekable(),
+    };
+    let mut charset = BTreeSet::new();
+    loop {
+        parser.skip_separators();
+        match parser.chars.peek().copied() {
+            None => break,
+            Some((offset, '[')) => {
+                parser.chars.next();
+                let first = parser.codepoint()?;
+                let last = parser.codepoint()?;
+                parser.expect(']')?;
+                if first > last {
+                    return Err(format!("reversed range at {}", offset));
+                }
+                charset.extend(first..=last);
+            }
+            Some((_, '"')) => {
+                parser.chars.next();
+                loop {
+                    if let Some(&(_, '"')) = parser.chars.peek() {
+                        parser.chars.next();
+                        break;
+                    }
+                    charset.insert(parser.literal_char()?);
+                }
+            }
+            Some(_) => {
+                charset.insert(parser.codepoint()?);
+            }
+        }
+    }
+    Ok(charset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(spec: &str) -> String {
+        parse_charset(spec).unwrap().into_iter().collect()
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse_charset("[0x20, 0x7e]").unwrap().len(), 95);
+        assert_eq!(chars("[0x41, 0x44]"), "ABCD");
+        assert_eq!(chars("['a' 'c']"), "abc");
+        assert_eq!(chars("[48,50]"), "012");
+        assert_eq!(chars("[ 0X61 , 0x61 ]"), "a");
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(chars("'a'"), "a");
+        assert_eq!(chars(r"'\''"), "'");
+        assert_eq!(chars(r"'\\'"), "\\");
+        assert_eq!(chars(r#""cab""#), "abc");
+        assert_eq!(chars(r#""a\"b\n""#), "\n\"ab");
+        assert_eq!(chars(r#""\t\0\r""#), "\0\t\r");
+        assert_eq!(chars(r#""""#), "");
+    }
+
+    #[test]
+    fn separators() {
+        assert_eq!(chars("65,66 67\n\t'D',,\"E\" [70, 71]"), "ABCDEFG");
+        assert_eq!(chars(" , 0x41 ,"), "A");
+        assert_eq!(chars(""), "");
+        assert_eq!(chars("'a','a' 97"), "a");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse_charset("'a").is_err());
+        assert!(parse_charset("'ab'").is_err());
+        assert!(parse_charset(r#""abc"#).is_err());
+        assert!(parse_charset(r#""abc\"#).is_err());
+        assert!(parse_charset("[0x41, 0x44").is_err());
+        assert!(parse_charset("[0x41]").is_err());
+        assert!(parse_charset("0xd800").is_err());
+        assert!(parse_charset("0x110000").is_err());
+        assert!(parse_charset("12ab").is_err());
+        assert!(parse_charset("a").is_err());
+        assert_eq!(
+            parse_charset("'a' [0x7e, 0x20]"),
+            Err("reversed range at 4".into())
+        );
+    }
+}
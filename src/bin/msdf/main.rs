@@ -0,0 +1,456 @@
+//! Command-line interface for generating a single distance field, similar to
+//! the single-glyph mode of msdfgen.
+
+mod atlas;
+mod charset;
+
+use std::{env, fs, process};
+
+use image::{GrayImage, RgbImage, RgbaImage};
+use msdf::{
+    bitmap::{BitmapBuf, Channel},
+    correction::{ErrorCorrectionConfig, ErrorCorrectionMode},
+    frame::Framing,
+    raster::{DistanceMapping, FieldType, Rasterizer, YAxis},
+    shape::{ColoringStrategy, FillRule, Shape, Vec2},
+    svg::{parse_document, parse_path},
+};
+use ttf_parser::{Face, GlyphId};
+
+const USAGE: &str = "\
+Usage: msdf <mode> <input> [options]
+       msdf atlas -font <file> [options]
+
+Modes:
+  bitmap | sdf | psdf | msdf | mtsdf
+  atlas                    Generates an atlas of many glyphs (see
+                           `msdf atlas -help`).
+
+Input:
+  -font <file> <char>      Glyph of a font. <char> is a character, a codepoint
+                           (U+0041, 0x41 or 65) or a glyph index (g36).
+  -path <data>             SVG path data (the y-axis points downwards).
+  -svg <file>              Filled shapes of an SVG document.
+
+Options:
+  -o <file>                Output file (default: output.png, or output.bin).
+  -format <png|binfloat>   PNG image or raw little-endian f32 values, row by
+                           row from the top (default: png).
+  -size <width> <height>   Size of the output in pixels (default: 64 64).
+  -pxrange <range>         Distance range in pixels (default: 2).
+  -scale <scale>           Scale from shape units to pixels.
+  -translate <x> <y>       Translation in shape units.
+  -autoframe               Fits the shape into the output (the default, unless
+                           -scale is given).
+  -yflip                   Flips the output vertically.
+  -fillrule <nonzero|evenodd|positive>
+                           The fill rule (default: nonzero, or the one of the
+                           SVG document).
+  -overlap                 Combines the distances of overlapping contours.
+  -normalize               Orients the outer contours clockwise and the holes
+                           counter-clockwise.
+  -preprocess              Resolves overlapping contours and self-intersections
+                           of the shape.
+  -coloringstrategy <simple|inktrap|distance>
+  -angle <angle>           Corner angle threshold in radians (default: 3).
+  -seed <n>                Seed of the edge coloring (default: 0).
+  -errorcorrection <disabled|indiscriminate|edgepriority|edgeonly>
+  -testrender <file> <width> <height>
+                           Renders the distance field back into a bitmap.
+  -help                    Prints this help.
+";
+
+enum Input {
+    Font(String, String),
+    Path(String),
+    Svg(String),
+}
+
+#[derive(PartialEq)]
+enum Format {
+    Png,
+    BinFloat,
+}
+
+struct Options {
+    field_type: FieldType,
+    input: Option<Input>,
+    output: Option<String>,
+    format: Format,
+    size: (usize, usize),
+    range: f64,
+    scale: Option<f64>,
+    translate: Option<(f64, f64)>,
+    autoframe: bool,
+    y_flip: bool,
+    fill_rule: Option<FillRule>,
+    overlap: bool,
+    preprocess: bool,
+    normalize: bool,
+    coloring: ColoringStrategy,
+    angle: f64,
+    seed: u64,
+    correction: ErrorCorrectionMode,
+    test_render: Option<(String, usize, usize)>,
+}
+
+fn parse<T: std::str::FromStr>(value: Option<String>, name: &str) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("missing value for {}", name))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{}` for {}", value, name))
+}
+
+fn parse_field_type(mode: &str) -> Result<FieldType, String> {
+    match mode {
+        "bitmap" => Ok(FieldType::Bitmap),
+        "sdf" => Ok(FieldType::Sdf),
+        "psdf" => Ok(FieldType::Psdf),
+        "msdf" => Ok(FieldType::Msdf),
+        "mtsdf" => Ok(FieldType::Mtsdf),
+        mode => Err(format!("unknown mode `{}`", mode)),
+    }
+}
+
+fn parse_fill_rule(rule: &str) -> Result<FillRule, String> {
+    match rule {
+        "nonzero" => Ok(FillRule::NonZero),
+        "evenodd" => Ok(FillRule::EvenOdd),
+        "positive" => Ok(FillRule::Positive),
+        rule => Err(format!("unknown fill rule `{}`", rule)),
+    }
+}
+
+fn parse_coloring(strategy: &str) -> Result<ColoringStrategy, String> {
+    match strategy {
+        "simple" => Ok(ColoringStrategy::Simple),
+        "inktrap" => Ok(ColoringStrategy::InkTrap),
+        "distance" => Ok(ColoringStrategy::ByDistance),
+        strategy => Err(format!("unknown strategy `{}`", strategy)),
+    }
+}
+
+fn parse_args(mode: &str, mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let field_type = parse_field_type(mode)?;
+    let mut options = Options {
+        field_type,
+        input: None,
+        output: None,
+        format: Format::Png,
+        size: (64, 64),
+        range: 2.0,
+        scale: None,
+        translate: None,
+        autoframe: false,
+        y_flip: false,
+        fill_rule: None,
+        overlap: false,
+        preprocess: false,
+        normalize: false,
+        coloring: ColoringStrategy::Simple,
+        angle: 3.0,
+        seed: 0,
+        correction: ErrorCorrectionConfig::<f64>::default().mode,
+        test_render: None,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-font" => {
+                let file = parse(args.next(), "-font")?;
+                let glyph = parse(args.next(), "-font")?;
+                options.input = Some(Input::Font(file, glyph));
+            }
+            "-path" => options.input = Some(Input::Path(parse(args.next(), "-path")?)),
+            "-svg" => options.input = Some(Input::Svg(parse(args.next(), "-svg")?)),
+            "-o" => options.output = Some(parse(args.next(), "-o")?),
+            "-format" => {
+                options.format = match parse::<String>(args.next(), "-format")?.as_str() {
+                    "png" => Format::Png,
+                    "binfloat" => Format::BinFloat,
+                    format => return Err(format!("unknown format `{}`", format)),
+                }
+            }
+            "-size" => options.size = (parse(args.next(), "-size")?, parse(args.next(), "-size")?),
+            "-pxrange" => options.range = parse(args.next(), "-pxrange")?,
+            "-scale" => options.scale = Some(parse(args.next(), "-scale")?),
+            "-translate" => {
+                options.translate = Some((
+                    parse(args.next(), "-translate")?,
+                    parse(args.next(), "-translate")?,
+                ))
+            }
+            "-autoframe" => options.autoframe = true,
+            "-yflip" => options.y_flip = true,
+            "-fillrule" => {
+                options.fill_rule = Some(parse_fill_rule(&parse::<String>(
+                    args.next(),
+                    "-fillrule",
+                )?)?)
+            }
+            "-overlap" => options.overlap = true,
+            "-preprocess" => options.preprocess = true,
+            "-normalize" => options.normalize = true,
+            "-coloringstrategy" => {
+                options.coloring =
+                    parse_coloring(&parse::<String>(args.next(), "-coloringstrategy")?)?
+            }
+            "-angle" => options.angle = parse(args.next(), "-angle")?,
+            "-seed" => options.seed = parse(args.next(), "-seed")?,
+            "-errorcorrection" => {
+                options.correction =
+                    match parse::<String>(args.next(), "-errorcorrection")?.as_str() {
+                        "disabled" => ErrorCorrectionMode::Disabled,
+                        "indiscriminate" => ErrorCorrectionMode::Indiscriminate,
+                        "edgepriority" => ErrorCorrectionMode::EdgePriority,
+                        "edgeonly" => ErrorCorrectionMode::EdgeOnly,
+                        mode => return Err(format!("unknown error correction `{}`", mode)),
+                    }
+            }
+            "-testrender" => {
+                options.test_render = Some((
+                    parse(args.next(), "-testrender")?,
+                    parse(args.next(), "-testrender")?,
+                    parse(args.next(), "-testrender")?,
+                ))
+            }
+            "-help" | "--help" | "-h" => {
+                print!("{}", USAGE);
+                process::exit(0);
+            }
+            arg => return Err(format!("unknown argument `{}`", arg)),
+        }
+    }
+    if options.size.0 == 0 || options.size.1 == 0 {
+        return Err("the size has to be positive".into());
+    }
+    if options.range <= 0.0 || !options.range.is_finite() {
+        return Err("the range has to be positive".into());
+    }
+    if let Some((_, width, height)) = options.test_render {
+        if width == 0 || height == 0 {
+            return Err("the size of the test render has to be positive".into());
+        }
+    }
+    if let Some(scale) = options.scale {
+        if scale <= 0.0 || !scale.is_finite() {
+            return Err("the scale has to be positive".into());
+        }
+    }
+    Ok(options)
+}
+
+fn parse_glyph(face: &Face, glyph: &str) -> Result<GlyphId, String> {
+    let invalid = || format!("invalid glyph `{}`", glyph);
+    if let Some(index) = glyph.strip_prefix('g').filter(|index| !index.is_empty()) {
+        return index.parse().map(GlyphId).map_err(|_| invalid());
+    }
+    let mut chars = glyph.chars();
+    let c = match (chars.next(), chars.next()) {
+        (Some(c), None) => c,
+        _ => {
+            let code = if let Some(hex) = glyph
+                .strip_prefix("U+")
+                .or_else(|| glyph.strip_prefix("0x"))
+            {
+                u32::from_str_radix(hex, 16)
+            } else {
+                glyph.parse()
+            };
+            code.ok()
+                .and_then(std::char::from_u32)
+                .ok_or_else(invalid)?
+        }
+    };
+    face.glyph_index(c)
+        .ok_or_else(|| format!("the font has no glyph for {:?}", c))
+}
+
+/// Loads the shape, whether its y-axis points downwards, and its fill rule.
+fn load_shape(input: &Option<Input>) -> Result<(Shape<f64>, bool, FillRule), String> {
+    match input {
+        None => Err("missing input (-font, -path or -svg)".into()),
+        Some(Input::Font(file, glyph)) => {
+            let data = fs::read(file).map_err(|e| format!("{}: {}", file, e))?;
+            let face = Face::from_slice(&data, 0).map_err(|e| format!("{}: {}", file, e))?;
+            let glyph_id = parse_glyph(&face, glyph)?;
+            let mut builder = Shape::builder();
+            face.outline_glyph(glyph_id, &mut builder);
+            let shape = builder.finish().ok_or("the glyph has no outline")?;
+            Ok((shape, false, FillRule::NonZero))
+        }
+        Some(Input::Path(data)) => {
+            let shape = parse_path(data).map_err(|e| format!("invalid path: {}", e))?;
+            Ok((shape, true, FillRule::NonZero))
+        }
+        Some(Input::Svg(file)) => {
+            let svg = fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+            let document = parse_document(&svg).map_err(|e| format!("{}: {}", file, e))?;
+            Ok((document.shape, true, document.fill_rule))
+        }
+    }
+}
+
+/// Returns the scale and translation, which fit the shape (and the distance
+/// range) into the output.
+fn autoframe(shape: &Shape<f64>, options: &Options) -> Result<(f64, (f64, f64)), String> {
+    let framing = Framing::new(options.range);
+    let (width, height) = options.size;
+    let frame = match options.scale {
+        Some(scale) => framing.center(shape.aabr(), width, height, Vec2::broadcast(scale)),
+        None => framing
+            .fit(shape.aabr(), width, height)
+            .ok_or("cannot fit the shape and the range into the output")?,
+    };
+    Ok((frame.scale.x, (frame.translate.x, frame.translate.y)))
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let (mut shape, y_down, fill_rule) = load_shape(&options.input)?;
+    let fill_rule = options.fill_rule.unwrap_or(fill_rule);
+    if options.normalize {
+        shape.normalize();
+    }
+    if options.preprocess {
+        // an empty result has no edges, so the shape is kept
+        if let Some(resolved) = shape.resolve_overlaps(fill_rule) {
+            shape = resolved;
+        }
+    }
+    let (scale, translate) = if options.autoframe || options.scale.is_none() {
+        let (scale, translate) = autoframe(&shape, &options)?;
+        (scale, options.translate.unwrap_or(translate))
+    } else {
+        (
+            options.scale.unwrap_or(1.0),
+            options.translate.unwrap_or((0.0, 0.0)),
+        )
+    };
+    // shapes with a downwards y-axis are upright without flipping
+    let y_axis = if y_down != options.y_flip {
+        YAxis::Down
+    } else {
+        YAxis::Up
+    };
+    let rasterizer = Rasterizer::new()
+        .with_scale(scale)
+        .with_translate(translate.0, translate.1)
+        .with_y_axis(y_axis)
+        .with_fill_rule(fill_rule)
+        .with_overlap_support(options.overlap);
+
+    let (width, height) = options.size;
+    let field_type = options.field_type;
+    let mut bitmap = BitmapBuf::<f32>::new(width, height, field_type.channels());
+    let mapping = DistanceMapping::Normalized;
+    if field_type.is_multi_channel() {
+        shape.color_edges(options.coloring, options.angle, options.seed);
+    }
+    rasterizer.rasterize(field_type, &shape, options.range, mapping, &mut bitmap);
+    if field_type.is_multi_channel() {
+        let config = ErrorCorrectionConfig {
+            mode: options.correction,
+            ..Default::default()
+        };
+        rasterizer.correct_msdf_errors(&shape, options.range, mapping, &mut bitmap, &config);
+    }
+
+    let output = options.output.clone().unwrap_or_else(|| {
+        match options.format {
+            Format::Png => "output.png",
+            Format::BinFloat => "output.bin",
+        }
+        .into()
+    });
+    match options.format {
+        Format::Png => {
+            let (w, h) = (width as u32, height as u32);
+            let data: Vec<u8> = bitmap
+                .as_slice()
+                .iter()
+                .map(|&value| u8::from_value(value))
+                .collect();
+            let result = match field_type.channels() {
+                1 => GrayImage::from_raw(w, h, data).unwrap().save(&output),
+                3 => RgbImage::from_raw(w, h, data).unwrap().save(&output),
+                _ => RgbaImage::from_raw(w, h, data).unwrap().save(&output),
+            };
+            result.map_err(|e| format!("{}: {}", output, e))?;
+        }
+        Format::BinFloat => {
+            let data: Vec<u8> = bitmap
+                .as_slice()
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect();
+            fs::write(&output, data).map_err(|e| format!("{}: {}", output, e))?;
+        }
+    }
+
+    if let Some((file, width, height)) = &options.test_render {
+        let mut render = GrayImage::new(*width as u32, *height as u32);
+        rasterizer.render_distance_field(&bitmap, options.range, mapping, &mut render);
+        render.save(file).map_err(|e| format!("{}: {}", file, e))?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let result = match args.next().as_deref() {
+        Some("atlas") => atlas::parse_args(args).and_then(atlas::run),
+        Some("-help") | Some("--help") | Some("-h") | None => {
+            print!("{}", USAGE);
+            return;
+        }
+        Some(mode) => parse_args(mode, args).and_then(run),
+    };
+    if let Err(error) = result {
+        eprintln!("msdf: {}", error);
+        eprintln!("Try `msdf -help` for more information.");
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Result<Options, String> {
+        parse_args("msdf", args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn valid_arguments() {
+        let options = args("-size 32 16 -pxrange 4.5 -scale 2 -testrender r.png 64 64").unwrap();
+        assert_eq!(options.size, (32, 16));
+        assert_eq!(options.range, 4.5);
+        assert_eq!(options.scale, Some(2.0));
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        for &arg in &[
+            "-size 0 0",
+            "-size 32 0",
+            "-size 32",
+            "-pxrange 0",
+            "-pxrange -2",
+            "-pxrange NaN",
+            "-pxrange inf",
+            "-scale 0",
+            "-testrender r.png 0 64",
+            "-unknown",
+        ] {
+            assert!(args(arg).is_err(), "{}", arg);
+        }
+    }
+
+    #[test]
+    fn invalid_atlas_arguments() {
+        let args = |args: &str| atlas::parse_args(args.split_whitespace().map(String::from));
+        assert!(args("-size 24 -pxrange 3 -dimensions 256 128").is_ok());
+        for &arg in &["-size 0", "-size NaN", "-pxrange 0", "-dimensions 0 128"] {
+            assert!(args(arg).is_err(), "{}", arg);
+        }
+    }
+}
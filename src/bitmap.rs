@@ -0,0 +1,404 @@
+//! Output targets for the `Rasterizer`.
+//!
+//! A `Bitmap` stores the pixels of each row contiguously, with the channels of
+//! a pixel interleaved. Row `0` is the top row.
+
+use alloc::vec::Vec;
+use num_traits::{real::Real, NumCast};
+
+use crate::math::{max, min};
+use crate::raster::MaybeSync;
+
+/// The value type of a single channel of a `Bitmap`.
+///
+/// Integer channels map the range `0..=1` to their full range, so they should
+/// be used with `DistanceMapping::Normalized`. Floating point channels store
+/// the values as they are.
+pub trait Channel: Copy + PartialOrd + MaybeSync {
+    fn from_value<S: Real + From<u16>>(value: S) -> Self;
+    fn to_value<S: Real + From<u16>>(self) -> S;
+}
+
+impl Channel for u8 {
+    #[inline]
+    fn from_value<S: Real + From<u16>>(value: S) -> Self {
+        let value = value * From::from(256);
+        max(S::zero(), min(value, From::from(255))).to_u8().unwrap()
+    }
+    #[inline]
+    fn to_value<S: Real + From<u16>>(self) -> S {
+        <S as From<u16>>::from(self as u16) / From::from(256)
+    }
+}
+
+impl Channel for u16 {
+    #[inline]
+    fn from_value<S: Real + From<u16>>(value: S) -> Self {
+        let _256: S = From::from(256);
+        let value = value * _256 * _256;
+        max(S::zero(), min(value, From::from(u16::MAX)))
+            .to_u16()
+            .unwrap()
+    }
+    #[inline]
+    fn to_value<S: Real + From<u16>>(self) -> S {
+        let _256: S = From::from(256);
+        <S as From<u16>>::from(self) / (_256 * _256)
+    }
+}
+
+impl Channel for f32 {
+    #[inline]
+    fn from_value<S: Real + From<u16>>(value: S) -> Self {
+        NumCast::from(value).unwrap()
+    }
+    #[inline]
+    fn to_value<S: Real + From<u16>>(self) -> S {
+        NumCast::from(self).unwrap()
+    }
+}
+
+impl Channel for f64 {
+    #[inline]
+    fn from_value<S: Real + From<u16>>(value: S) -> Self {
+        NumCast::from(value).unwrap()
+    }
+    #[inline]
+    fn to_value<S: Real + From<u16>>(self) -> S {
+        NumCast::from(self).unwrap()
+    }
+}
+
+/// A target, the `Rasterizer` can write into.
+pub trait Bitmap {
+    type Channel: Channel;
+
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    /// The number of channels per pixel.
+    fn channels(&self) -> usize;
+    /// Returns the `width * channels` values of the row `y`.
+    fn row(&self, y: usize) -> &[Self::Channel];
+    /// Returns the `width * channels` values of the row `y`.
+    fn row_mut(&mut self, y: usize) -> &mut [Self::Channel];
+    /// Returns the values of all rows and their stride, if they are stored in
+    /// a single slice, where the row `y` starts at `y * stride`.
+    ///
+    /// This allows the rows to be rendered concurrently.
+    #[inline]
+    fn rows_mut(&mut self) -> Option<(&mut [Self::Channel], usize)> {
+        None
+    }
+}
+
+/// An owned `Bitmap`, backed by a `Vec`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitmapBuf<T> {
+    data: Vec<T>,
+    width: usize,
+    height: usize,
+    channels: usize,
+}
+
+impl<T: Channel + Default> BitmapBuf<T> {
+    pub fn new(width: usize, height: usize, channels: usize) -> Self {
+        BitmapBuf {
+            data: alloc::vec![T::default(); width * height * channels],
+            width,
+            height,
+            channels,
+        }
+    }
+}
+
+impl<T: Channel> BitmapBuf<T> {
+    /// Wraps `data`, which has to hold `width * height * channels` values.
+    pub fn from_vec(data: Vec<T>, width: usize, height: usize, channels: usize) -> Self {
+        assert_eq!(data.len(), width * height * channels, "invalid bitmap size");
+        BitmapBuf {
+            data,
+            width,
+            height,
+            channels,
+        }
+    }
+
+    #[inline]
+    pub fn pixel(&self, x: usize, y: usize) -> &[T] {
+        let start = (y * self.width + x) * self.channels;
+        &self.data[start..start + self.channels]
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+
+    #[inline]
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+}
+
+impl<T: Channel> Bitmap for BitmapBuf<T> {
+    type Channel = T;
+
+    #[inline]
+    fn width(&self) -> usize {
+        self.width
+    }
+    #[inline]
+    fn height(&self) -> usize {
+        self.height
+    }
+    #[inline]
+    fn channels(&self) -> usize {
+        self.channels
+    }
+    #[inline]
+    fn row(&self, y: usize) -> &[T] {
+        let stride = self.width * self.channels;
+        &self.data[y * stride..(y + 1) * stride]
+    }
+    #[inline]
+    fn row_mut(&mut self, y: usize) -> &mut [T] {
+        let stride = self.width * self.channels;
+        &mut self.data[y * stride..(y + 1) * stride]
+    }
+    #[inline]
+    fn rows_mut(&mut self) -> Option<(&mut [T], usize)> {
+        Some((&mut self.data, self.width * self.channels))
+    }
+}
+
+/// A `Bitmap`, that borrows an existing buffer.
+#[derive(Debug)]
+pub struct BitmapRef<'a, T> {
+    data: &'a mut [T],
+    width: usize,
+    height: usize,
+    channels: usize,
+}
+
+impl<'a, T: Channel> BitmapRef<'a, T> {
+    /// Wraps `data`, which has to hold at least `width * height * channels`
+    /// values.
+    pub fn new(data: &'a mut [T], width: usize, height: usize, channels: usize) -> Self {
+        assert!(data.len() >= width * height * channels, "buffer too small");
+        BitmapRef {
+            data,
+            width,
+            height,
+            channels,
+        }
+    }
+}
+
+impl<'a, T: Channel> Bitmap for BitmapRef<'a, T> {
+    type Channel = T;
+
+    #[inline]
+    fn width(&self) -> usize {
+        self.width
+    }
+    #[inline]
+    fn height(&self) -> usize {
+        self.height
+    }
+    #[inline]
+    fn channels(&self) -> usize {
+        self.channels
+    }
+    #[inline]
+    fn row(&self, y: usize) -> &[T] {
+        let stride = self.width * self.channels;
+        &self.data[y * stride..(y + 1) * stride]
+    }
+    #[inline]
+    fn row_mut(&mut self, y: usize) -> &mut [T] {
+        let stride = self.width * self.channels;
+        &mut self.data[y * stride..(y + 1) * stride]
+    }
+    #[inline]
+    fn rows_mut(&mut self) -> Option<(&mut [T], usize)> {
+        let stride = self.width * self.channels;
+        Some((&mut self.data[..self.height * stride], stride))
+    }
+}
+
+/// A rectangular section of another `Bitmap`.
+#[derive(Debug)]
+pub struct BitmapView<'a, B> {
+    bitmap: &'a mut B,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl<'a, B: Bitmap> BitmapView<'a, B> {
+    /// Borrows the section of `bitmap` with the top-left pixel `(x, y)`.
+    pub fn new(bitmap: &'a mut B, x: usize, y: usize, width: usize, height: usize) -> Self {
+        assert!(
+            x + width <= bitmap.width() && y + height <= bitmap.height(),
+            "view out of bounds"
+        );
+        BitmapView {
+            bitmap,
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+impl<'a, B: Bitmap> Bitmap for BitmapView<'a, B> {
+    type Channel = B::Channel;
+
+    #[inline]
+    fn width(&self) -> usize {
+        self.width
+    }
+    #[inline]
+    fn height(&self) -> usize {
+        self.height
+    }
+    #[inline]
+    fn channels(&self) -> usize {
+        self.bitmap.channels()
+    }
+    #[inline]
+    fn row(&self, y: usize) -> &[B::Channel] {
+        let channels = self.bitmap.channels();
+        let start = self.x * channels;
+        &self.bitmap.row(self.y + y)[start..start + self.width * channels]
+    }
+    #[inline]
+    fn row_mut(&mut self, y: usize) -> &mut [B::Channel] {
+        let channels = self.bitmap.channels();
+        let start = self.x * channels;
+        &mut self.bitmap.row_mut(self.y + y)[start..start + self.width * channels]
+    }
+    #[inline]
+    fn rows_mut(&mut self) -> Option<(&mut [B::Channel], usize)> {
+        let channels = self.bitmap.channels();
+        let (data, stride) = self.bitmap.rows_mut()?;
+        // an empty view may start right after the last row
+        let start = min(self.y * stride + self.x * channels, data.len());
+        Some((&mut data[start..], stride))
+    }
+}
+
+/// A `Bitmap` of borrowed rows, e.g. a section of another bitmap, which can
+/// be rendered independently of the other sections.
+#[cfg(feature = "parallel")]
+#[derive(Debug)]
+pub(crate) struct BitmapRows<'a, T> {
+    rows: Vec<&'a mut [T]>,
+    width: usize,
+    channels: usize,
+}
+
+#[cfg(feature = "parallel")]
+impl<'a, T: Channel> Bitmap for BitmapRows<'a, T> {
+    type Channel = T;
+
+    #[inline]
+    fn width(&self) -> usize {
+        self.width
+    }
+    #[inline]
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
+    #[inline]
+    fn channels(&self) -> usize {
+        self.channels
+    }
+    #[inline]
+    fn row(&self, y: usize) -> &[T] {
+        self.rows[y]
+    }
+    #[inline]
+    fn row_mut(&mut self, y: usize) -> &mut [T] {
+        self.rows[y]
+    }
+}
+
+/// Splits the rows `data` with `stride` into the non-overlapping `sections`,
+/// given as `(x, y, width, height)` in pixels.
+#[cfg(feature = "parallel")]
+pub(crate) fn split_sections<'a, T>(
+    data: &'a mut [T],
+    stride: usize,
+    channels: usize,
+    sections: &[(usize, usize, usize, usize)],
+) -> Vec<BitmapRows<'a, T>> {
+    let mut result: Vec<BitmapRows<'a, T>> = sections
+        .iter()
+        .map(|&(_, _, width, height)| BitmapRows {
+            rows: Vec::with_capacity(height),
+            width,
+            channels,
+        })
+        .collect();
+    // the rows of all sections, ordered by their position
+    let mut spans: Vec<(usize, usize, usize)> = Vec::new();
+    for (index, &(x, y, _, height)) in sections.iter().enumerate() {
+        spans.extend((y..y + height).map(|y| (y, x, index)));
+    }
+    spans.sort_unstable();
+    let mut rows = data.chunks_mut(stride);
+    let mut next_row = 0;
+    // the remainder of the current row, which starts at `offset`
+    let mut rest: &'a mut [T] = &mut [];
+    let mut offset = 0;
+    for (y, x, index) in spans {
+        if y >= next_row {
+            rest = rows.nth(y - next_row).expect("section out of bounds");
+            next_row = y + 1;
+            offset = 0;
+        }
+        let start = x * channels;
+        let width = result[index].width * channels;
+        assert!(start >= offset, "overlapping sections");
+        let (_, tail) = core::mem::take(&mut rest).split_at_mut(start - offset);
+        let (row, tail) = tail.split_at_mut(width);
+        result[index].rows.push(row);
+        rest = tail;
+        offset = start + width;
+    }
+    result
+}
+
+/// A `Bitmap`, which doesn't expose its storage, so it is always rendered row
+/// by row.
+#[cfg(test)]
+pub(crate) struct RowByRow<B>(pub B);
+
+#[cfg(test)]
+impl<B: Bitmap> Bitmap for RowByRow<B> {
+    type Channel = B::Channel;
+
+    fn width(&self) -> usize {
+        self.0.width()
+    }
+    fn height(&self) -> usize {
+        self.0.height()
+    }
+    fn channels(&self) -> usize {
+        self.0.channels()
+    }
+    fn row(&self, y: usize) -> &[B::Channel] {
+        self.0.row(y)
+    }
+    fn row_mut(&mut self, y: usize) -> &mut [B::Channel] {
+        self.0.row_mut(y)
+    }
+}
@@ -0,0 +1,602 @@
+//! Export of atlas metadata as AngelCode BMFont descriptor (`.fnt`).
+//!
+//! All values are rounded to whole pixels. Glyphs are identified by their
+//! codepoint, or by their glyph id (or index in the layout) if they have none.
+//! The text format includes the `distanceField` line, which is understood by
+//! common distance field font loaders. The binary format has no such block, so
+//! it is appended as an extra block of type 6, which readers skipping unknown
+//! blocks ignore.
+//!
+//! The text format can't quote names containing `"`, and the binary format
+//! stores most values in 16 bits, so both writers return a `BmFontError` for
+//! values they can't represent instead of writing a broken descriptor.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::{self, Write};
+use num_traits::real::Real;
+
+use crate::{
+    atlas::AtlasLayout,
+    metrics::{FontMetrics, KerningPair},
+    raster::FieldType,
+};
+
+/// An error while writing a BMFont descriptor.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BmFontError {
+    /// The named string (`face` or `page`) contains a character, which the
+    /// format can't store: a `"` or a line break in the text format, or a
+    /// null character in the binary format.
+    InvalidString(&'static str),
+    /// The named value doesn't fit into its field of the binary format.
+    OutOfRange(&'static str),
+    /// The writer returned an error.
+    Write,
+}
+
+impl From<fmt::Error> for BmFontError {
+    fn from(_: fmt::Error) -> Self {
+        BmFontError::Write
+    }
+}
+
+impl fmt::Display for BmFontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BmFontError::InvalidString(name) => {
+                write!(f, "`{}` contains a character, which can't be stored", name)
+            }
+            BmFontError::OutOfRange(name) => {
+                write!(f, "`{}` is out of the range of the binary format", name)
+            }
+            BmFontError::Write => f.write_str("failed to write the descriptor"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BmFontError {}
+
+/// A character of the `chars` block.
+struct Char {
+    id: u32,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    x_offset: i32,
+    y_offset: i32,
+    x_advance: i32,
+}
+
+/// The values of the `info` and `common` blocks.
+struct Common {
+    size: i32,
+    line_height: i32,
+    base: i32,
+}
+
+/// Rounds `value` to whole pixels, saturating at the bounds of `i32`, so
+/// huge values are caught by the range checks of `to_binary`.
+fn round<S: Real>(value: S) -> i32 {
+    let value = value.round();
+    value.to_i32().unwrap_or(if value > S::zero() {
+        i32::MAX
+    } else if value < S::zero() {
+        i32::MIN
+    } else {
+        0
+    })
+}
+
+fn char_id<S>(layout: &AtlasLayout<S>, index: usize) -> u32 {
+    let glyph = &layout.glyphs[index];
+    match (glyph.codepoint, glyph.glyph_id) {
+        (Some(c), _) => c as u32,
+        (None, Some(glyph_id)) => glyph_id as u32,
+        (None, None) => index as u32,
+    }
+}
+
+fn common<S: Real>(layout: &AtlasLayout<S>, metrics: &FontMetrics<S>) -> Common {
+    Common {
+        size: round(metrics.em_size * layout.scale),
+        line_height: round(metrics.line_height * layout.scale),
+        base: round(metrics.ascender * layout.scale),
+    }
+}
+
+fn chars<S: Real>(layout: &AtlasLayout<S>, base: i32) -> Vec<Char> {
+    layout
+        .glyphs
+        .iter()
+        .enumerate()
+        .map(|(index, glyph)| {
+            let x_advance = round(glyph.advance * layout.scale);
+            let id = char_id(layout, index);
+            match &glyph.placement {
+                Some(placement) => Char {
+                    id,
+                    x: placement.x,
+                    y: placement.y,
+                    width: placement.width,
+                    height: placement.height,
+                    x_offset: round(placement.bounds.min.x * layout.scale),
+                    y_offset: base - round(placement.bounds.max.y * layout.scale),
+                    x_advance,
+                },
+                None => Char {
+                    id,
+                    x: 0,
+                    y: 0,
+                    width: 0,
+                    height: 0,
+                    x_offset: 0,
+                    y_offset: 0,
+                    x_advance,
+                },
+            }
+        })
+        .collect()
+}
+
+fn type_name(field_type: FieldType) -> &'static str {
+    match field_type {
+        FieldType::Bitmap => "bitmap",
+        FieldType::Sdf => "sdf",
+        FieldType::Psdf => "psdf",
+        FieldType::Msdf => "msdf",
+        FieldType::Mtsdf => "mtsdf",
+    }
+}
+
+/// Returns `value`, if it contains none of the `invalid` characters.
+fn checked<'a>(
+    value: &'a str,
+    name: &'static str,
+    invalid: &[char],
+) -> Result<&'a str, BmFontError> {
+    if value.contains(invalid) {
+        Err(BmFontError::InvalidString(name))
+    } else {
+        Ok(value)
+    }
+}
+
+/// Writes the metadata of an atlas of `field_type` with the given `layout` in
+/// the BMFont text format.
+///
+/// `face` is the name of the font and `page` the file name of the atlas image.
+/// Both are quoted, so they must not contain `"` or line breaks.
+pub fn write_text<W: Write, S: Real>(
+    out: &mut W,
+    layout: &AtlasLayout<S>,
+    field_type: FieldType,
+    metrics: &FontMetrics<S>,
+    kerning: &[KerningPair<S>],
+    face: &str,
+    page: &str,
+) -> Result<(), BmFontError> {
+    let invalid = ['"', '\n', '\r'];
+    let face = checked(face, "face", &invalid)?;
+    let page = checked(page, "page", &invalid)?;
+    let common = common(layout, metrics);
+    writeln!(
+        out,
+        "info face=\"{}\" size={} bold=0 italic=0 charset=\"\" unicode=1 stretchH=100 \
+         smooth=1 aa=1 padding=0,0,0,0 spacing=0,0 outline=0",
+        face, common.size
+    )?;
+    writeln!(
+        out,
+        "common lineHeight={} base={} scaleW={} scaleH={} pages=1 packed=0 \
+         alphaChnl=0 redChnl=0 greenChnl=0 blueChnl=0",
+        common.line_height, common.base, layout.width, layout.height
+    )?;
+    writeln!(out, "page id=0 file=\"{}\"", page)?;
+    writeln!(
+        out,
+        "distanceField fieldType={} distanceRange={}",
+        type_name(field_type),
+        round(layout.range)
+    )?;
+
+    let chars = chars(layout, common.base);
+    writeln!(out, "chars count={}", chars.len())?;
+    for c in &chars {
+        writeln!(
+            out,
+            "char id={} x={} y={} width={} height={} xoffset={} yoffset={} xadvance={} \
+             page=0 chnl=15",
+            c.id, c.x, c.y, c.width, c.height, c.x_offset, c.y_offset, c.x_advance
+        )?;
+    }
+
+    writeln!(out, "kernings count={}", kerning.len())?;
+    for pair in kerning {
+        writeln!(
+            out,
+            "kerning first={} second={} amount={}",
+            char_id(layout, pair.first),
+            char_id(layout, pair.second),
+            round(pair.advance * layout.scale)
+        )?;
+    }
+    Ok(())
+}
+
+/// Appends a block of the binary format.
+fn block(out: &mut Vec<u8>, block_type: u8, content: &[u8]) {
+    out.push(block_type);
+    out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    out.extend_from_slice(content);
+}
+
+/// Appends `value` as `u16`, or fails with the `name` of the value.
+fn push_u16<T>(out: &mut Vec<u8>, value: T, name: &'static str) -> Result<(), BmFontError>
+where
+    u16: TryFrom<T>,
+{
+    let value = u16::try_from(value).map_err(|_| BmFontError::OutOfRange(name))?;
+    out.extend_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+/// Appends `value` as `i16`, or fails with the `name` of the value.
+fn push_i16(out: &mut Vec<u8>, value: i32, name: &'static str) -> Result<(), BmFontError> {
+    let value = i16::try_from(value).map_err(|_| BmFontError::OutOfRange(name))?;
+    out.extend_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+/// Appends `value` as null-terminated string.
+fn push_str(out: &mut Vec<u8>, value: &str, name: &'static str) -> Result<(), BmFontError> {
+    out.extend_from_slice(checked(value, name, &['\0'])?.as_bytes());
+    out.push(0);
+    Ok(())
+}
+
+/// Returns the metadata of an atlas of `field_type` with the given `layout` in
+/// the BMFont binary format (version 3).
+///
+/// The distance field is described by a trailing block of type 6 (not part of
+/// the format) with the distance range (`u16`) and the null-terminated name of
+/// the field type. See `write_text` for the meaning of `face` and `page`,
+/// which must not contain null characters here.
+///
+/// Fails, if a size, position, offset or kerning amount doesn't fit into the
+/// 16 bits of its field (e.g. for atlases larger than 65535 pixels).
+pub fn to_binary<S: Real>(
+    layout: &AtlasLayout<S>,
+    field_type: FieldType,
+    metrics: &FontMetrics<S>,
+    kerning: &[KerningPair<S>],
+    face: &str,
+    page: &str,
+) -> Result<Vec<u8>, BmFontError> {
+    let common = common(layout, metrics);
+    let mut out = Vec::new();
+    out.extend_from_slice(b"BMF\x03");
+
+    let mut info = Vec::new();
+    push_i16(&mut info, common.size, "size")?;
+    // smooth and unicode (bits are counted from the highest bit)
+    info.push(0b1100_0000);
+    // charset
+    info.push(0);
+    info.extend_from_slice(&100u16.to_le_bytes());
+    // aa, padding (up, right, down, left), spacing (horizontal, vertical), outline
+    info.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+    push_str(&mut info, face, "face")?;
+    block(&mut out, 1, &info);
+
+    let mut header = Vec::new();
+    push_u16(&mut header, common.line_height, "lineHeight")?;
+    push_u16(&mut header, common.base, "base")?;
+    push_u16(&mut header, layout.width, "scaleW")?;
+    push_u16(&mut header, layout.height, "scaleH")?;
+    // pages
+    header.extend_from_slice(&1u16.to_le_bytes());
+    // packed, alpha, red, green and blue channel
+    header.extend_from_slice(&[0, 0, 0, 0, 0]);
+    block(&mut out, 2, &header);
+
+    let mut pages = Vec::new();
+    push_str(&mut pages, page, "page")?;
+    block(&mut out, 3, &pages);
+
+    let mut chars_block = Vec::new();
+    for c in chars(layout, common.base) {
+        chars_block.extend_from_slice(&c.id.to_le_bytes());
+        push_u16(&mut chars_block, c.x, "x")?;
+        push_u16(&mut chars_block, c.y, "y")?;
+        push_u16(&mut chars_block, c.width, "width")?;
+        push_u16(&mut chars_block, c.height, "height")?;
+        push_i16(&mut chars_block, c.x_offset, "xoffset")?;
+        push_i16(&mut chars_block, c.y_offset, "yoffset")?;
+        push_i16(&mut chars_block, c.x_advance, "xadvance")?;
+        // page and channels
+        chars_block.extend_from_slice(&[0, 15]);
+    }
+    block(&mut out, 4, &chars_block);
+
+    if !kerning.is_empty() {
+        let mut kerning_block = Vec::new();
+        for pair in kerning {
+            kerning_block.extend_from_slice(&char_id(layout, pair.first).to_le_bytes());
+            kerning_block.extend_from_slice(&char_id(layout, pair.second).to_le_bytes());
+            let amount = round(pair.advance * layout.scale);
+            push_i16(&mut kerning_block, amount, "amount")?;
+        }
+        block(&mut out, 5, &kerning_block);
+    }
+
+    let mut distance_field = Vec::new();
+    push_u16(&mut distance_field, round(layout.range), "distanceRange")?;
+    push_str(&mut distance_field, type_name(field_type), "fieldType")?;
+    block(&mut out, 6, &distance_field);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use vek::{Aabr, Vec2};
+
+    use super::*;
+    use crate::atlas::{AtlasGlyph, Placement};
+
+    fn layout() -> AtlasLayout<f64> {
+        let placement = |x, y, size, min: (f64, f64), max: (f64, f64)| {
+            Some(Placement {
+                x,
+                y,
+                width: size,
+                height: size + 2,
+                bounds: Aabr {
+                    min: Vec2::new(min.0, min.1),
+                    max: Vec2::new(max.0, max.1),
+                },
+            })
+        };
+        AtlasLayout {
+            width: 64,
+            height: 32,
+            scale: 0.5,
+            range: 4.0,
+            glyphs: alloc::vec![
+                AtlasGlyph {
+                    glyph_id: Some(36),
+                    codepoint: Some('A'),
+                    advance: 20.0,
+                    placement: placement(1, 2, 10, (-2.0, -4.0), (18.0, 20.0)),
+                },
+                AtlasGlyph {
+                    glyph_id: Some(3),
+                    codepoint: Some(' '),
+                    advance: 8.0,
+                    placement: None,
+                },
+                AtlasGlyph {
+                    glyph_id: Some(7),
+                    codepoint: None,
+                    advance: 10.0,
+                    placement: placement(12, 0, 6, (0.0, 0.0), (8.0, 8.0)),
+                },
+            ],
+        }
+    }
+
+    fn metrics() -> FontMetrics<f64> {
+        FontMetrics {
+            em_size: 32.0,
+            line_height: 40.0,
+            ascender: 28.0,
+            ..FontMetrics::default()
+        }
+    }
+
+    fn kerning(advance: f64) -> [KerningPair<f64>; 1] {
+        [KerningPair {
+            first: 0,
+            second: 2,
+            advance,
+        }]
+    }
+
+    #[test]
+    fn text_format() {
+        let mut out = String::new();
+        let (layout, metrics) = (layout(), metrics());
+        let kerning = kerning(-3.0);
+        write_text(
+            &mut out,
+            &layout,
+            FieldType::Msdf,
+            &metrics,
+            &kerning,
+            "Test",
+            "test.png",
+        )
+        .unwrap();
+        let expected = "\
+info face=\"Test\" size=16 bold=0 italic=0 charset=\"\" unicode=1 stretchH=100 smooth=1 aa=1 \
+padding=0,0,0,0 spacing=0,0 outline=0
+common lineHeight=20 base=14 scaleW=64 scaleH=32 pages=1 packed=0 alphaChnl=0 redChnl=0 \
+greenChnl=0 blueChnl=0
+page id=0 file=\"test.png\"
+distanceField fieldType=msdf distanceRange=4
+chars count=3
+char id=65 x=1 y=2 width=10 height=12 xoffset=-1 yoffset=4 xadvance=10 page=0 chnl=15
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=4 page=0 chnl=15
+char id=7 x=12 y=0 width=6 height=8 xoffset=0 yoffset=10 xadvance=5 page=0 chnl=15
+kernings count=1
+kerning first=65 second=7 amount=-2
+";
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn quotes_in_text_format() {
+        let (layout, metrics) = (layout(), metrics());
+        let write = |face: &str, page: &str| {
+            write_text(
+                &mut String::new(),
+                &layout,
+                FieldType::Sdf,
+                &metrics,
+                &[],
+                face,
+                page,
+            )
+        };
+        assert_eq!(
+            write("a \"b\"", "c.png"),
+            Err(BmFontError::InvalidString("face"))
+        );
+        assert_eq!(
+            write("a", "c\n.png"),
+            Err(BmFontError::InvalidString("page"))
+        );
+        assert_eq!(write("a 'b'", "c d.png"), Ok(()));
+    }
+
+    /// Splits the binary format into its blocks.
+    fn blocks(data: &[u8]) -> Vec<(u8, &[u8])> {
+        assert_eq!(&data[..4], b"BMF\x03");
+        let mut blocks = Vec::new();
+        let mut rest = &data[4..];
+        while !rest.is_empty() {
+            let size = u32::from_le_bytes([rest[1], rest[2], rest[3], rest[4]]) as usize;
+            blocks.push((rest[0], &rest[5..5 + size]));
+            rest = &rest[5 + size..];
+        }
+        blocks
+    }
+
+    fn u16_at(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([data[offset], data[offset + 1]])
+    }
+
+    fn i16_at(data: &[u8], offset: usize) -> i16 {
+        i16::from_le_bytes([data[offset], data[offset + 1]])
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    }
+
+    #[test]
+    fn binary_format() {
+        let (layout, metrics) = (layout(), metrics());
+        let kerning = kerning(-3.0);
+        let data = to_binary(
+            &layout,
+            FieldType::Msdf,
+            &metrics,
+            &kerning,
+            "Test",
+            "test.png",
+        )
+        .unwrap();
+        let parsed = blocks(&data);
+        let types: Vec<u8> = parsed.iter().map(|(block_type, _)| *block_type).collect();
+        assert_eq!(types, [1, 2, 3, 4, 5, 6]);
+
+        let info = parsed[0].1;
+        assert_eq!(info.len(), 14 + 5);
+        assert_eq!(i16_at(info, 0), 16);
+        assert_eq!(u16_at(info, 4), 100);
+        assert_eq!(&info[14..], b"Test\0");
+
+        let common = parsed[1].1;
+        assert_eq!(common.len(), 15);
+        let values: Vec<u16> = (0..5).map(|i| u16_at(common, 2 * i)).collect();
+        assert_eq!(values, [20, 14, 64, 32, 1]);
+
+        assert_eq!(parsed[2].1, b"test.png\0");
+
+        let chars = parsed[3].1;
+        assert_eq!(chars.len(), 3 * 20);
+        let records: Vec<_> = chars
+            .chunks_exact(20)
+            .map(|c| {
+                let sizes = [u16_at(c, 4), u16_at(c, 6), u16_at(c, 8), u16_at(c, 10)];
+                let offsets = [i16_at(c, 12), i16_at(c, 14), i16_at(c, 16)];
+                (u32_at(c, 0), sizes, offsets, [c[18], c[19]])
+            })
+            .collect();
+        assert_eq!(
+            records,
+            [
+                (65, [1, 2, 10, 12], [-1, 4, 10], [0, 15]),
+                (32, [0, 0, 0, 0], [0, 0, 4], [0, 15]),
+                (7, [12, 0, 6, 8], [0, 10, 5], [0, 15]),
+            ]
+        );
+
+        let kerning = parsed[4].1;
+        assert_eq!(kerning.len(), 10);
+        assert_eq!((u32_at(kerning, 0), u32_at(kerning, 4)), (65, 7));
+        assert_eq!(i16_at(kerning, 8), -2);
+
+        let distance_field = parsed[5].1;
+        assert_eq!(u16_at(distance_field, 0), 4);
+        assert_eq!(&distance_field[2..], b"msdf\0");
+
+        // the kerning block is left out without kerning pairs
+        let data = to_binary(&layout, FieldType::Sdf, &metrics, &[], "Test", "test.png").unwrap();
+        let types: Vec<u8> = blocks(&data)
+            .iter()
+            .map(|(block_type, _)| *block_type)
+            .collect();
+        assert_eq!(types, [1, 2, 3, 4, 6]);
+    }
+
+    #[test]
+    fn values_out_of_range() {
+        let metrics = metrics();
+        let binary = |layout: &AtlasLayout<f64>, kerning: &[KerningPair<f64>], face: &str| {
+            to_binary(layout, FieldType::Msdf, &metrics, kerning, face, "test.png")
+        };
+        let mut wide = layout();
+        wide.width = 70_000;
+        assert_eq!(
+            binary(&wide, &[], "Test"),
+            Err(BmFontError::OutOfRange("scaleW"))
+        );
+        let mut far = layout();
+        far.glyphs[2].placement.as_mut().unwrap().y = 65_536;
+        assert_eq!(binary(&far, &[], "Test"), Err(BmFontError::OutOfRange("y")));
+        let mut large = layout();
+        large.scale = 5000.0;
+        assert_eq!(
+            binary(&large, &[], "Test"),
+            Err(BmFontError::OutOfRange("size"))
+        );
+        let kerning = kerning(1e6);
+        assert_eq!(
+            binary(&layout(), &kerning, "Test"),
+            Err(BmFontError::OutOfRange("amount"))
+        );
+        let kerning = KerningPair {
+            first: 0,
+            second: 2,
+            advance: f64::INFINITY,
+        };
+        assert_eq!(
+            binary(&layout(), &[kerning], "Test"),
+            Err(BmFontError::OutOfRange("amount"))
+        );
+        assert_eq!(
+            binary(&layout(), &[], "a\0b"),
+            Err(BmFontError::InvalidString("face"))
+        );
+    }
+}
@@ -0,0 +1,589 @@
+//! Boolean operations on shapes.
+//!
+//! The edges of the operands are split at their intersections, and only the
+//! pieces, which separate the inside of the result from its outside, are kept
+//! and linked into new contours.
+
+use alloc::vec::Vec;
+use approx::RelativeEq;
+use core::cmp::Ordering;
+use num_traits::{real::Real, FloatConst};
+use smallvec::SmallVec;
+
+use crate::{
+    math::{cross, max, min, mix},
+    shape::{
+        Aabr, CubicBezier2, Edge, EdgeColor, EdgeSegment, FillRule, LineSegment2, Point2,
+        QuadraticBezier2, Shape,
+    },
+};
+
+/// The maximum number of pairs of curve pieces, which are compared to find the
+/// intersections of two curves. Curves, which exceed it, are (nearly)
+/// coincident and are only split at the endpoints of each other.
+const MAX_CURVE_STEPS: usize = 4096;
+
+/// A parameter on an edge, and the point there.
+type Split<S> = (S, Point2<S>);
+
+/// Selects how the areas of two shapes are combined by `Shape::boolean`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BooleanOp {
+    /// The area, which is inside of either shape.
+    Union,
+    /// The area, which is inside of both shapes.
+    Intersection,
+    /// The area of the first shape, which is outside of the second one.
+    Difference,
+}
+
+impl BooleanOp {
+    fn is_inside(self, a: bool, b: bool) -> bool {
+        match self {
+            BooleanOp::Union => a || b,
+            BooleanOp::Intersection => a && b,
+            BooleanOp::Difference => a && !b,
+        }
+    }
+}
+
+/// The size of the larger side of `aabr`.
+fn extent<S: Real>(aabr: Aabr<S>) -> S {
+    max(aabr.max.x - aabr.min.x, aabr.max.y - aabr.min.y)
+}
+
+fn overlaps<S: Real>(a: Aabr<S>, b: Aabr<S>, tolerance: S) -> bool {
+    a.min.x - tolerance <= b.max.x
+        && b.min.x - tolerance <= a.max.x
+        && a.min.y - tolerance <= b.max.y
+        && b.min.y - tolerance <= a.max.y
+}
+
+/// Returns the parameters of the intersection of the lines through `a0`, `a1`
+/// and through `b0`, `b1`, or `None` if they are parallel.
+fn line_intersection<S: Real>(
+    a0: Point2<S>,
+    a1: Point2<S>,
+    b0: Point2<S>,
+    b1: Point2<S>,
+) -> Option<(S, S)> {
+    let (a, b) = (a1 - a0, b1 - b0);
+    let denominator = cross(a, b);
+    if denominator == S::zero() {
+        return None;
+    }
+    let offset = b0 - a0;
+    Some((
+        cross(offset, b) / denominator,
+        cross(offset, a) / denominator,
+    ))
+}
+
+/// Refines the parameters `s` and `t` of an intersection of `a` and `b` with
+/// Newton's method, unless that moves the points on both edges apart.
+fn refine_intersection<S>(a: &EdgeSegment<S>, b: &EdgeSegment<S>, s: S, t: S) -> (S, S)
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    let clamp = |value: S| max(S::zero(), min(value, S::one()));
+    let error = |s: S, t: S| a.evaluate(s).distance(b.evaluate(t));
+    let (mut refined_s, mut refined_t) = (s, t);
+    for _ in 0..4 {
+        let (direction_a, direction_b) = (a.direction(refined_s), b.direction(refined_t));
+        let denominator = cross(direction_a, direction_b);
+        if denominator == S::zero() {
+            break;
+        }
+        let offset = b.evaluate(refined_t) - a.evaluate(refined_s);
+        refined_s = clamp(refined_s + cross(offset, direction_b) / denominator);
+        refined_t = clamp(refined_t + cross(offset, direction_a) / denominator);
+    }
+    if error(refined_s, refined_t) <= error(s, t) {
+        (refined_s, refined_t)
+    } else {
+        (s, t)
+    }
+}
+
+/// Returns `segment` with its endpoints replaced, to close the gaps left by
+/// rounding errors.
+fn with_endpoints<S: Copy>(
+    segment: EdgeSegment<S>,
+    start: Point2<S>,
+    end: Point2<S>,
+) -> EdgeSegment<S> {
+    match segment {
+        EdgeSegment::Linear(_) => LineSegment2 { start, end }.into(),
+        EdgeSegment::Quadratic(e) => QuadraticBezier2 {
+            start,
+            ctrl: e.ctrl,
+            end,
+        }
+        .into(),
+        EdgeSegment::Cubic(e) => CubicBezier2 {
+            start,
+            ctrl0: e.ctrl0,
+            ctrl1: e.ctrl1,
+            end,
+        }
+        .into(),
+    }
+}
+
+/// Returns the part of `segment` between the parameters `from` and `to`.
+fn sub_segment<S>(segment: &EdgeSegment<S>, from: S, to: S) -> EdgeSegment<S>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    let mut part = *segment;
+    if to < S::one() {
+        part = part.split(to)[0];
+    }
+    if from > S::zero() {
+        part = part.split(from / to)[1];
+    }
+    part
+}
+
+/// Finds the intersections of the edges `a` and `b` as the parameters on
+/// both edges and the point. Curves are subdivided down to pieces of the size
+/// `precision`, and intersections within `tolerance` of an endpoint are moved
+/// onto it.
+fn intersections<S>(
+    a: &EdgeSegment<S>,
+    b: &EdgeSegment<S>,
+    precision: S,
+    tolerance: S,
+) -> SmallVec<[(S, S, Point2<S>); 4]>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    let mut result: SmallVec<[(S, S, Point2<S>); 4]> = SmallVec::new();
+    let snap = |point: Point2<S>| {
+        [a.start(), a.end(), b.start(), b.end()]
+            .iter()
+            .copied()
+            .find(|endpoint| endpoint.distance(point) <= tolerance)
+            .unwrap_or(point)
+    };
+
+    // endpoints, which touch the other edge
+    for &(param, endpoint) in &[(S::zero(), a.start()), (S::one(), a.end())] {
+        let other = b.closest_param(endpoint, tolerance);
+        if b.evaluate(other).distance(endpoint) <= tolerance {
+            result.push((param, other, endpoint));
+        }
+    }
+    for &(param, endpoint) in &[(S::zero(), b.start()), (S::one(), b.end())] {
+        let other = a.closest_param(endpoint, tolerance);
+        if a.evaluate(other).distance(endpoint) <= tolerance {
+            result.push((other, param, endpoint));
+        }
+    }
+
+    if let (EdgeSegment::Linear(e), EdgeSegment::Linear(f)) = (a, b) {
+        if let Some((s, t)) = line_intersection(e.start, e.end, f.start, f.end) {
+            if S::zero() <= s && s <= S::one() && S::zero() <= t && t <= S::one() {
+                result.push((s, t, snap(a.evaluate(s))));
+            }
+        }
+        return result;
+    }
+
+    // subdivide both curves, until the pieces, which overlap, are smaller
+    // than the precision
+    let half = S::one() / (S::one() + S::one());
+    let mut crossings: SmallVec<[(S, S, Point2<S>); 4]> = SmallVec::new();
+    let mut stack: SmallVec<[(S, S, S, S); 32]> = SmallVec::new();
+    stack.push((S::zero(), S::one(), S::zero(), S::one()));
+    let mut steps = 0;
+    while let Some((a0, a1, b0, b1)) = stack.pop() {
+        steps += 1;
+        if steps > MAX_CURVE_STEPS {
+            return result;
+        }
+        let (part_a, part_b) = (sub_segment(a, a0, a1), sub_segment(b, b0, b1));
+        let (aabr_a, aabr_b) = (part_a.aabr(), part_b.aabr());
+        if !overlaps(aabr_a, aabr_b, precision) {
+            continue;
+        }
+        let (extent_a, extent_b) = (extent(aabr_a), extent(aabr_b));
+        if extent_a <= precision && extent_b <= precision {
+            let (s, t) =
+                line_intersection(part_a.start(), part_a.end(), part_b.start(), part_b.end())
+                    .map_or((half, half), |(s, t)| {
+                        let clamp = |value: S| max(S::zero(), min(value, S::one()));
+                        (clamp(s), clamp(t))
+                    });
+            let (s, t) = refine_intersection(a, b, mix(a0, a1, s), mix(b0, b1, t));
+            let point = snap(a.evaluate(s));
+            // neighboring pieces find the same intersection
+            if !crossings
+                .iter()
+                .any(|(_, _, other)| other.distance(point) <= tolerance)
+            {
+                crossings.push((s, t, point));
+            }
+        } else if extent_a >= extent_b {
+            let middle = (a0 + a1) * half;
+            stack.push((a0, middle, b0, b1));
+            stack.push((middle, a1, b0, b1));
+        } else {
+            let middle = (b0 + b1) * half;
+            stack.push((a0, a1, b0, middle));
+            stack.push((a0, a1, middle, b1));
+        }
+    }
+    result.extend(crossings);
+    result
+}
+
+/// Whether `point` is inside of `shape`.
+fn is_inside<S>(shape: &Shape<S>, point: Point2<S>, fill_rule: FillRule) -> bool
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    shape.scanline(point.y).is_filled(point.x, fill_rule)
+}
+
+/// Splits the edges of all `operands` at their intersections and keeps the
+/// pieces, where `inside` (called with the fill of every operand under its
+/// fill rule) differs on both sides. The pieces are oriented, so that the inside is on their
+/// right-hand side (the side of the positive distances), and linked into
+/// contours.
+fn clip<S, F>(operands: &[(&Shape<S>, FillRule)], inside: F) -> Option<Shape<S>>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+    F: Fn(&[bool]) -> bool,
+{
+    let edges: Vec<&Edge<S>> = operands
+        .iter()
+        .flat_map(|(shape, _)| shape.edges())
+        .collect();
+    let mut aabr = operands[0].0.aabr();
+    for (shape, _) in operands {
+        aabr.expand_to_contain(shape.aabr());
+    }
+    let _16 = <S as From<u16>>::from(16);
+    let precision = extent(aabr) * S::epsilon().powf(<S as From<u16>>::from(3) / From::from(4));
+    // the distance, within which points are merged
+    let tolerance = precision * _16;
+    // the distance of the points, where the fill on both sides of a piece is
+    // sampled
+    let offset = tolerance * _16;
+    let half = S::one() / (S::one() + S::one());
+
+    // the parameters and points, where each edge is split
+    let mut splits: Vec<SmallVec<[Split<S>; 2]>> = alloc::vec![SmallVec::new(); edges.len()];
+    for i in 0..edges.len() {
+        for j in i + 1..edges.len() {
+            if !overlaps(edges[i].aabr(), edges[j].aabr(), tolerance) {
+                continue;
+            }
+            for (s, t, point) in intersections(edges[i], edges[j], precision, tolerance) {
+                splits[i].push((s, point));
+                splits[j].push((t, point));
+            }
+        }
+    }
+
+    let mut pieces: Vec<EdgeSegment<S>> = Vec::new();
+    let mut fill: SmallVec<[bool; 2]> = SmallVec::new();
+    for (edge, splits) in edges.iter().zip(splits.iter_mut()) {
+        splits.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        let (start, end) = (edge.start(), edge.end());
+        let mut points: SmallVec<[Split<S>; 4]> = SmallVec::new();
+        points.push((S::zero(), start));
+        for &(param, point) in splits.iter() {
+            let previous = points[points.len() - 1].1;
+            if previous.distance(point) > tolerance && end.distance(point) > tolerance {
+                points.push((param, point));
+            }
+        }
+        points.push((S::one(), end));
+
+        for window in points.windows(2) {
+            let ((from, start), (to, end)) = (window[0], window[1]);
+            let piece = with_endpoints(sub_segment(edge, from, to), start, end);
+            let middle = piece.evaluate(half);
+            let direction = piece.direction(half);
+            if extent(piece.aabr()) <= tolerance || direction == Point2::zero() {
+                continue;
+            }
+            let normal = Point2::new(direction.y, -direction.x).normalized() * offset;
+            let mut sample = |point: Point2<S>| {
+                fill.clear();
+                fill.extend(
+                    operands
+                        .iter()
+                        .map(|&(shape, fill_rule)| is_inside(shape, point, fill_rule)),
+                );
+                inside(&fill)
+            };
+            let piece = match (sample(middle + normal), sample(middle - normal)) {
+                (true, false) => piece,
+                (false, true) => piece.reversed(),
+                _ => continue,
+            };
+            // coincident edges of the operands
+            let is_duplicate = pieces.iter().any(|other| {
+                other.start().distance(piece.start()) <= tolerance
+                    && other.end().distance(piece.end()) <= tolerance
+                    && other.evaluate(half).distance(middle) <= tolerance
+            });
+            if !is_duplicate {
+                pieces.push(piece);
+            }
+        }
+    }
+
+    // link the pieces into contours
+    let mut result: Vec<Edge<S>> = Vec::with_capacity(pieces.len());
+    let mut used = alloc::vec![false; pieces.len()];
+    for first in 0..pieces.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let contour_start = result.len();
+        let start = pieces[first].start();
+        result.push(Edge::new(pieces[first], EdgeColor::WHITE, true));
+        loop {
+            let end = result[result.len() - 1].end();
+            if end.distance(start) <= tolerance {
+                break;
+            }
+            let next = (0..pieces.len())
+                .filter(|&i| !used[i])
+                .map(|i| (i, pieces[i].start().distance(end)))
+                .filter(|&(_, distance)| distance <= tolerance)
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+            match next {
+                Some((i, _)) => {
+                    used[i] = true;
+                    let piece = with_endpoints(pieces[i], end, pieces[i].end());
+                    result.push(Edge::new(piece, EdgeColor::WHITE, false));
+                }
+                None => {
+                    // an open contour, which is closed with a line
+                    result.push(Edge::new(
+                        LineSegment2 {
+                            start: end,
+                            end: start,
+                        }
+                        .into(),
+                        EdgeColor::WHITE,
+                        false,
+                    ));
+                    break;
+                }
+            }
+        }
+        // close the remaining gap exactly
+        let last = result.len() - 1;
+        let segment = result[last].segment;
+        result[last] = Edge::new(
+            with_endpoints(segment, segment.start(), start),
+            EdgeColor::WHITE,
+            last == contour_start,
+        );
+    }
+    Shape::from_edges(result)
+}
+
+impl<S> Shape<S>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    /// Combines the areas of this shape and `other` as selected by `op`,
+    /// where the inside of both shapes is decided by `fill_rule`.
+    ///
+    /// The edges are split at their intersections, so the result has no
+    /// overlapping contours. Its contours are oriented clockwise (with the
+    /// y-axis pointing upwards), holes counter-clockwise, and its edges have
+    /// to be colored again. Returns `None`, if the result is empty.
+    pub fn boolean(
+        &self,
+        other: &Shape<S>,
+        op: BooleanOp,
+        fill_rule: FillRule,
+    ) -> Option<Shape<S>> {
+        clip(&[(self, fill_rule), (other, fill_rule)], |fill| {
+            op.is_inside(fill[0], fill[1])
+        })
+    }
+
+    /// Returns the area, which is inside of this shape or `other` (see
+    /// `boolean`).
+    pub fn union(&self, other: &Shape<S>) -> Option<Shape<S>> {
+        self.boolean(other, BooleanOp::Union, FillRule::default())
+    }
+
+    /// Returns the area, which is inside of this shape and `other` (see
+    /// `boolean`).
+    pub fn intersection(&self, other: &Shape<S>) -> Option<Shape<S>> {
+        self.boolean(other, BooleanOp::Intersection, FillRule::default())
+    }
+
+    /// Returns the area of this shape, which is outside of `other` (see
+    /// `boolean`).
+    pub fn difference(&self, other: &Shape<S>) -> Option<Shape<S>> {
+        self.boolean(other, BooleanOp::Difference, FillRule::default())
+    }
+
+    /// Returns the outline of the area of this shape (the union of all of its
+    /// contours), without overlapping contours and self-intersections.
+    ///
+    /// This is an alternative to `Rasterizer::with_overlap_support`, which
+    /// only has to be done once (see `boolean` for the result).
+    pub fn resolve_overlaps(&self, fill_rule: FillRule) -> Option<Shape<S>> {
+        clip(&[(self, fill_rule)], |fill| fill[0])
+    }
+}
+
+/// Returns the union of the areas of all `shapes`, where the inside of each
+/// shape is decided by its own fill rule (see `Shape::boolean`).
+#[cfg(feature = "roxmltree")]
+pub(crate) fn union_all<S>(shapes: &[(&Shape<S>, FillRule)]) -> Option<Shape<S>>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    if shapes.is_empty() {
+        return None;
+    }
+    clip(shapes, |fill| fill.contains(&true))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use approx::assert_relative_eq;
+    use core::f64::consts::PI;
+
+    use super::*;
+    use crate::svg::parse_path;
+
+    fn square(x: f64, y: f64, size: f64) -> Shape<f64> {
+        let path = alloc::format!(
+            "M {x0} {y0} L {x0} {y1} L {x1} {y1} L {x1} {y0} Z",
+            x0 = x,
+            y0 = y,
+            x1 = x + size,
+            y1 = y + size
+        );
+        parse_path(&path).unwrap()
+    }
+
+    /// A circle made of four cubic curves.
+    fn circle(x: f64, y: f64, radius: f64) -> Shape<f64> {
+        let (r, k) = (radius, 0.552_284_749_8 * radius);
+        let mut path = alloc::format!("M {} {}", x + r, y);
+        for &[c0x, c0y, c1x, c1y, ex, ey] in &[
+            [r, k, k, r, 0.0, r],
+            [-k, r, -r, k, -r, 0.0],
+            [-r, -k, -k, -r, 0.0, -r],
+            [k, -r, r, -k, r, 0.0],
+        ] {
+            path += &alloc::format!(
+                " C {} {} {} {} {} {}",
+                x + c0x,
+                y + c0y,
+                x + c1x,
+                y + c1y,
+                x + ex,
+                y + ey
+            );
+        }
+        parse_path(&path).unwrap()
+    }
+
+    fn area(shape: &Shape<f64>) -> f64 {
+        shape.contours().iter().map(|c| c.signed_area()).sum()
+    }
+
+    fn windings(shape: &Shape<f64>) -> Vec<i32> {
+        let mut windings: Vec<i32> = shape.contours().iter().map(|c| c.winding()).collect();
+        windings.sort_unstable_by(|a, b| b.cmp(a));
+        windings
+    }
+
+    #[test]
+    fn union_of_overlapping_squares() {
+        let union = square(0.0, 0.0, 2.0).union(&square(1.0, 1.0, 2.0)).unwrap();
+        assert_relative_eq!(area(&union), 7.0, epsilon = 1e-9);
+        assert_eq!(windings(&union), [1]);
+        assert_eq!(union.edges().len(), 8);
+    }
+
+    #[test]
+    fn even_odd_hole() {
+        // both squares are clockwise
+        let shape = parse_path("M 0 0 L 0 4 L 4 4 L 4 0 Z M 1 1 L 1 3 L 3 3 L 3 1 Z").unwrap();
+        let resolved = shape.resolve_overlaps(FillRule::EvenOdd).unwrap();
+        assert_relative_eq!(area(&resolved), 12.0, epsilon = 1e-9);
+        assert_eq!(windings(&resolved), [1, -1]);
+        let resolved = shape.resolve_overlaps(FillRule::NonZero).unwrap();
+        assert_relative_eq!(area(&resolved), 16.0, epsilon = 1e-9);
+        assert_eq!(windings(&resolved), [1]);
+    }
+
+    #[test]
+    fn self_intersecting_bowtie() {
+        let bowtie = parse_path("M 0 0 L 2 2 L 2 0 L 0 2 Z").unwrap();
+        assert_relative_eq!(area(&bowtie), 0.0, epsilon = 1e-9);
+        let resolved = bowtie.resolve_overlaps(FillRule::NonZero).unwrap();
+        assert_eq!(windings(&resolved), [1, 1]);
+        for contour in resolved.contours() {
+            assert_relative_eq!(contour.signed_area(), 1.0, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn touching_operands() {
+        // sharing an edge
+        let a = square(0.0, 0.0, 1.0);
+        let b = square(1.0, 0.0, 1.0);
+        let union = a.union(&b).unwrap();
+        assert_relative_eq!(area(&union), 2.0, epsilon = 1e-9);
+        assert_eq!(windings(&union), [1]);
+        assert!(a.intersection(&b).is_none());
+        let difference = a.difference(&b).unwrap();
+        assert_relative_eq!(area(&difference), 1.0, epsilon = 1e-9);
+
+        // sharing a corner
+        let c = square(1.0, 1.0, 1.0);
+        let union = a.union(&c).unwrap();
+        assert_relative_eq!(area(&union), 2.0, epsilon = 1e-9);
+        assert!(a.intersection(&c).is_none());
+    }
+
+    #[test]
+    fn identical_operands() {
+        let a = circle(0.0, 0.0, 1.0);
+        let expected = area(&a).abs();
+        for op in &[BooleanOp::Union, BooleanOp::Intersection] {
+            let result = a.boolean(&a, *op, FillRule::NonZero).unwrap();
+            assert_relative_eq!(area(&result), expected, epsilon = 1e-9);
+            assert_eq!(windings(&result), [1], "{:?}", op);
+        }
+        assert!(a.difference(&a).is_none());
+        let square = square(0.0, 0.0, 1.0);
+        assert!(square.difference(&square).is_none());
+    }
+
+    #[test]
+    fn ring_of_circles() {
+        let ring = circle(0.0, 0.0, 1.0)
+            .difference(&circle(0.0, 0.0, 0.5))
+            .unwrap();
+        assert_relative_eq!(area(&ring), 0.75 * PI, epsilon = 1e-3);
+        assert_eq!(windings(&ring), [1, -1]);
+
+        // an inner circle, which is off center, but still inside
+        let ring = circle(0.0, 0.0, 1.0)
+            .difference(&circle(0.3, 0.2, 0.5))
+            .unwrap();
+        assert_relative_eq!(area(&ring), 0.75 * PI, epsilon = 1e-3);
+        assert_eq!(windings(&ring), [1, -1]);
+    }
+}
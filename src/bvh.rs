@@ -0,0 +1,290 @@
+//! A bounding volume hierarchy over the edges of a shape.
+//!
+//! The distance queries only need to look at the edges near the point, so the
+//! edges are grouped into a binary tree of bounding boxes, and subtrees, which
+//! are farther away than the closest edge found so far, are skipped.
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use num_traits::real::Real;
+use smallvec::SmallVec;
+use vek::Aabr;
+
+use crate::math::max;
+use crate::shape::{aabr_potentialli_contains_circle, Edge, Point2};
+
+/// The maximum number of edges in a leaf.
+const LEAF_SIZE: usize = 4;
+
+#[derive(Copy, Clone, Debug)]
+enum Node<S> {
+    /// The edges `order[start..end]`.
+    Leaf {
+        aabr: Aabr<S>,
+        start: usize,
+        end: usize,
+    },
+    /// The left child directly follows this node, the right one is at `right`.
+    Inner { aabr: Aabr<S>, right: usize },
+}
+
+impl<S: Copy> Node<S> {
+    #[inline]
+    fn aabr(&self) -> Aabr<S> {
+        match *self {
+            Node::Leaf { aabr, .. } | Node::Inner { aabr, .. } => aabr,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Bvh<S> {
+    nodes: Vec<Node<S>>,
+    /// The indices of the edges, ordered by the leaves.
+    order: Vec<usize>,
+}
+
+/// The squared distance of `point` to `aabr` (zero inside).
+#[inline]
+fn distance_squared<S: Real>(aabr: Aabr<S>, point: Point2<S>) -> S {
+    let dx = max(S::zero(), max(aabr.min.x - point.x, point.x - aabr.max.x));
+    let dy = max(S::zero(), max(aabr.min.y - point.y, point.y - aabr.max.y));
+    dx * dx + dy * dy
+}
+
+impl<S: Real> Bvh<S> {
+    /// Builds the hierarchy by splitting the edges at the median of their
+    /// centers along the longer axis.
+    pub fn new(edges: &[Edge<S>]) -> Self {
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(2 * edges.len() / LEAF_SIZE + 1),
+            order: (0..edges.len()).collect(),
+        };
+        if !edges.is_empty() {
+            bvh.build(edges, 0, edges.len());
+        }
+        bvh
+    }
+
+    fn build(&mut self, edges: &[Edge<S>], start: usize, end: usize) -> usize {
+        // the doubled center, which is good enough for ordering
+        let center = |i: usize| edges[i].aabr.min + edges[i].aabr.max;
+        let index = self.nodes.len();
+        let mut aabr = edges[self.order[start]].aabr;
+        let first = center(self.order[start]);
+        let mut centers = Aabr {
+            min: first,
+            max: first,
+        };
+        for &i in &self.order[start + 1..end] {
+            aabr.expand_to_contain(edges[i].aabr);
+            centers.expand_to_contain_point(center(i));
+        }
+        if end - start <= LEAF_SIZE {
+            self.nodes.push(Node::Leaf { aabr, start, end });
+            return index;
+        }
+        self.nodes.push(Node::Inner { aabr, right: 0 });
+        let split_x = centers.max.x - centers.min.x >= centers.max.y - centers.min.y;
+        let key = |i: usize| {
+            let c = center(i);
+            if split_x {
+                c.x
+            } else {
+                c.y
+            }
+        };
+        let mid = (start + end) / 2;
+        self.order[start..end].select_nth_unstable_by(mid - start, |&a, &b| {
+            key(a).partial_cmp(&key(b)).unwrap_or(Ordering::Equal)
+        });
+        self.build(edges, start, mid);
+        let right = self.build(edges, mid, end);
+        self.nodes[index] = Node::Inner { aabr, right };
+        index
+    }
+
+    /// Calls `f` for the edges, whose bounding box is within `bound` of
+    /// `point`, visiting nearer subtrees first.
+    ///
+    /// `f` is called with the index of the edge and returns the new bound,
+    /// usually the distance of the closest edge found so far.
+    pub fn visit<F>(&self, edges: &[Edge<S>], point: Point2<S>, mut bound: S, mut f: F)
+    where
+        F: FnMut(usize, &Edge<S>) -> S,
+    {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack: SmallVec<[usize; 32]> = SmallVec::new();
+        stack.push(0);
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !aabr_potentialli_contains_circle(node.aabr(), point, bound) {
+                continue;
+            }
+            match *node {
+                Node::Leaf { start, end, .. } => {
+                    for &i in &self.order[start..end] {
+                        let edge = &edges[i];
+                        if aabr_potentialli_contains_circle(edge.aabr, point, bound) {
+                            bound = f(i, edge);
+                        }
+                    }
+                }
+                Node::Inner { right, .. } => {
+                    let left = index + 1;
+                    let left_distance = distance_squared(self.nodes[left].aabr(), point);
+                    let right_distance = distance_squared(self.nodes[right].aabr(), point);
+                    // the nearer child is popped first
+                    if left_distance <= right_distance {
+                        stack.push(right);
+                        stack.push(left);
+                    } else {
+                        stack.push(left);
+                        stack.push(right);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::shape::{replaces, ColoringStrategy, EdgeCandidate, Point2, Shape, ShapeBuilder};
+
+    const EPSILON: f64 = 1e-6;
+
+    /// A shape of a few wavy rings, made of lines, quadratic and cubic
+    /// curves.
+    fn many_edges() -> Shape<f64> {
+        let mut builder = ShapeBuilder::new();
+        for (ring, &(cx, cy, radius)) in [(0.0, 0.0, 40.0), (90.0, 10.0, 30.0), (30.0, 60.0, 25.0)]
+            .iter()
+            .enumerate()
+        {
+            let n = 24 + 6 * ring;
+            let point = |i: f64| {
+                let angle = i / n as f64 * core::f64::consts::PI * 2.0;
+                let r = radius * (1.0 + 0.15 * (angle * 5.0).sin());
+                (cx + r * angle.cos(), cy + r * angle.sin())
+            };
+            let (x, y) = point(0.0);
+            builder.move_to(x, y);
+            for i in 0..n {
+                let i = i as f64;
+                let (x, y) = point(i + 1.0);
+                match i as usize % 3 {
+                    0 => builder.line_to(x, y),
+                    1 => {
+                        let (cx, cy) = point(i + 0.5);
+                        builder.quadratic_to(cx * 1.05, cy * 1.05, x, y)
+                    }
+                    _ => {
+                        let (cx0, cy0) = point(i + 0.3);
+                        let (cx1, cy1) = point(i + 0.7);
+                        builder.cubic_to(cx0 * 0.95, cy0 * 0.95, cx1 * 1.05, cy1 * 1.05, x, y)
+                    }
+                };
+            }
+            builder.close();
+        }
+        let mut shape = builder.finish().unwrap();
+        shape.color_edges(ColoringStrategy::Simple, 3.0, 0);
+        shape
+    }
+
+    fn sample_points(shape: &Shape<f64>) -> Vec<Point2<f64>> {
+        let aabr = shape.aabr();
+        let mut points = Vec::new();
+        for i in 0..=12 {
+            for j in 0..=12 {
+                let t = Point2::new(i as f64, j as f64) / 12.0;
+                let min = aabr.min - 20.0;
+                let size = aabr.max - aabr.min + 40.0;
+                points.push(min + size * t);
+            }
+        }
+        points
+    }
+
+    fn linear_closest_point(
+        shape: &Shape<f64>,
+        point: Point2<f64>,
+        max_distance: f64,
+    ) -> Option<(f64, Point2<f64>)> {
+        let mut best: Option<(f64, Point2<f64>)> = None;
+        for edge in shape.edges() {
+            let (distance, p) = edge.closest_point(point, EPSILON);
+            let closer = match best {
+                Some((best_distance, _)) => distance < best_distance,
+                None => true,
+            };
+            if distance <= max_distance && closer {
+                best = Some((distance, p));
+            }
+        }
+        best
+    }
+
+    /// The closest edge of `shape` of a color with `channel` (any color for
+    /// `None`) by a linear scan.
+    fn linear_closest_edge(
+        shape: &Shape<f64>,
+        point: Point2<f64>,
+        max_distance: f64,
+        channel: Option<usize>,
+    ) -> Option<f64> {
+        let mut best: Option<EdgeCandidate<f64>> = None;
+        for (index, edge) in shape.edges().iter().enumerate() {
+            match channel {
+                Some(channel) if !edge.color().has_channel(channel) => continue,
+                _ => {}
+            }
+            let (distance, param) = edge.signed_distance(point, EPSILON);
+            if distance.distance.abs() <= max_distance && replaces(&distance, index, &best) {
+                best = Some((distance, index, param));
+            }
+        }
+        best.map(|(mut distance, index, param)| {
+            shape.edges()[index].distance_to_pseudo_distance(&mut distance, point, param);
+            distance.distance
+        })
+    }
+
+    #[test]
+    fn queries_match_a_linear_scan() {
+        let shape = many_edges();
+        assert!(shape.edges().len() > 80);
+        for point in sample_points(&shape) {
+            for &max_distance in &[5.0, 1e9] {
+                assert_eq!(
+                    shape.closest_point(point, max_distance, EPSILON),
+                    linear_closest_point(&shape, point, max_distance),
+                    "closest point of {:?}",
+                    point
+                );
+                assert_eq!(
+                    shape.closest_pseudo_distance(point, max_distance, EPSILON),
+                    linear_closest_edge(&shape, point, max_distance, None),
+                    "pseudo-distance of {:?}",
+                    point
+                );
+            }
+            let expected: Vec<f64> = (0..3)
+                .map(|channel| {
+                    linear_closest_edge(&shape, point, f64::MAX, Some(channel)).unwrap_or(-f64::MAX)
+                })
+                .collect();
+            assert_eq!(
+                shape.multi_channel_distance(point, EPSILON)[..],
+                expected[..],
+                "multi-channel distance of {:?}",
+                point
+            );
+        }
+    }
+}
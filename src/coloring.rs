@@ -0,0 +1,630 @@
+use alloc::vec::Vec;
+use approx::RelativeEq;
+use num_traits::{real::Real, FloatConst};
+use vek::Vec2;
+
+use crate::{
+    math::{cross, max, min},
+    shape::{Edge, EdgeColor, EdgeSegment, Shape},
+};
+
+/// The number of steps used to approximate the length of, and the distance
+/// between edges.
+const EDGE_LENGTH_PRECISION: u16 = 4;
+
+/// The algorithm used by `Shape::color_edges`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ColoringStrategy {
+    /// Switches colors at every corner (see `Shape::color_edges_simple`).
+    Simple,
+    /// Like `Simple`, but short spans between two corners (like ink traps)
+    /// don't get a color of their own (see `Shape::color_edges_ink_trap`).
+    InkTrap,
+    /// Assigns colors so that edges, which are close to each other, are
+    /// colored differently (see `Shape::color_edges_by_distance`).
+    ByDistance,
+}
+
+impl Default for ColoringStrategy {
+    #[inline]
+    fn default() -> Self {
+        ColoringStrategy::Simple
+    }
+}
+
+fn is_corner<S: Real>(a: Vec2<S>, b: Vec2<S>, cross_threshold: S) -> bool {
+    a.dot(b) <= S::zero() || cross(a, b).abs() > cross_threshold
+}
+
+/// Maps `position` in `0..n` to the indices `0`, `1` or `2`, symmetrically.
+fn symmetrical_trichotomy(position: usize, n: usize) -> usize {
+    (3.0 + 2.875 * position as f64 / (n - 1) as f64 - 1.4375 + 0.5) as usize - 2
+}
+
+fn switch_color(color: &mut EdgeColor, seed: &mut u64, banned: EdgeColor) {
+    let combined = *color & banned;
+    if combined == EdgeColor::RED || combined == EdgeColor::GREEN || combined == EdgeColor::BLUE {
+        *color = combined ^ EdgeColor::WHITE;
+        return;
+    }
+    if *color == EdgeColor::BLACK || *color == EdgeColor::WHITE {
+        const START: [EdgeColor; 3] = [EdgeColor::CYAN, EdgeColor::MAGENTA, EdgeColor::YELLOW];
+        *color = START[(*seed % 3) as usize];
+        *seed /= 3;
+        return;
+    }
+    let shifted = color.bits() << (1 + (*seed & 1));
+    *color = EdgeColor::from_bits(shifted | shifted >> 3);
+    *seed >>= 1;
+}
+
+fn seed_extract2(seed: &mut u64) -> usize {
+    let value = (*seed & 1) as usize;
+    *seed >>= 1;
+    value
+}
+
+fn seed_extract3(seed: &mut u64) -> usize {
+    let value = (*seed % 3) as usize;
+    *seed /= 3;
+    value
+}
+
+fn estimate_edge_length<S>(segment: &EdgeSegment<S>) -> S
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    let steps = <S as From<u16>>::from(EDGE_LENGTH_PRECISION);
+    let mut length = S::zero();
+    let mut prev = segment.start();
+    for i in 1..=EDGE_LENGTH_PRECISION {
+        let current = segment.evaluate(<S as From<u16>>::from(i) / steps);
+        length = length + current.distance(prev);
+        prev = current;
+    }
+    length
+}
+
+fn edge_to_edge_distance<S>(a: &EdgeSegment<S>, b: &EdgeSegment<S>, epsilon: S) -> S
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    if a.start() == b.start() || a.start() == b.end() || a.end() == b.start() || a.end() == b.end()
+    {
+        return S::zero();
+    }
+    let steps = <S as From<u16>>::from(EDGE_LENGTH_PRECISION);
+    let mut distance = a.start().distance(b.start());
+    for i in 0..=EDGE_LENGTH_PRECISION {
+        let value = <S as From<u16>>::from(i) / steps;
+        distance = min(distance, a.closest_point(b.evaluate(value), epsilon).0);
+        distance = min(distance, b.closest_point(a.evaluate(value), epsilon).0);
+    }
+    distance
+}
+
+/// Returns a bit-mask of the colors (`0..3`), which are not used by any
+/// neighbor of `vertex`.
+fn vertex_possible_colors(coloring: &[usize], adjacency: &[bool], vertex: usize) -> usize {
+    let n = coloring.len();
+    let mut possible_colors = 7;
+    for (j, color) in coloring.iter().enumerate() {
+        if adjacency[vertex * n + j] {
+            possible_colors &= !(1 << color);
+        }
+    }
+    possible_colors
+}
+
+const FIRST_POSSIBLE_COLOR: [usize; 8] = [0, 0, 1, 0, 2, 2, 1, 0];
+
+/// Colors a graph, in which no vertex has more than two neighbors, with three
+/// colors.
+fn color_second_degree_graph(coloring: &mut [usize], adjacency: &[bool], seed: &mut u64) {
+    let n = coloring.len();
+    for i in 0..n {
+        let mut possible_colors = 7;
+        for j in 0..i {
+            if adjacency[i * n + j] {
+                possible_colors &= !(1 << coloring[j]);
+            }
+        }
+        coloring[i] = match possible_colors {
+            3 => seed_extract2(seed),
+            5 => (1 - seed_extract2(seed)) << 1,
+            6 => seed_extract2(seed) + 1,
+            7 => (seed_extract3(seed) + i) % 3,
+            _ => FIRST_POSSIBLE_COLOR[possible_colors],
+        };
+    }
+}
+
+/// Adds an edge between `a` and `b` to the graph, if the coloring can be
+/// adjusted so that both vertices get different colors.
+fn try_add_edge(coloring: &mut [usize], adjacency: &mut [bool], a: usize, b: usize) -> bool {
+    let n = coloring.len();
+    adjacency[a * n + b] = true;
+    adjacency[b * n + a] = true;
+    if coloring[a] != coloring[b] {
+        return true;
+    }
+    for &vertex in &[b, a] {
+        let possible_colors = vertex_possible_colors(coloring, adjacency, vertex);
+        if possible_colors != 0 {
+            coloring[vertex] = FIRST_POSSIBLE_COLOR[possible_colors];
+            return true;
+        }
+    }
+    adjacency[a * n + b] = false;
+    adjacency[b * n + a] = false;
+    false
+}
+
+/// Returns the indices of the edges, which start at a corner.
+fn find_corners<S>(contour: &[Edge<S>], cross_threshold: S) -> Vec<usize>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    let mut corners = Vec::new();
+    if let Some(last) = contour.last() {
+        let mut prev_direction = last.segment.direction(S::one());
+        for (index, edge) in contour.iter().enumerate() {
+            if is_corner(
+                prev_direction.normalized(),
+                edge.segment.direction(S::zero()).normalized(),
+                cross_threshold,
+            ) {
+                corners.push(index);
+            }
+            prev_direction = edge.segment.direction(S::one());
+        }
+    }
+    corners
+}
+
+/// Colors a contour with a single corner, splitting its edges when there are
+/// less than three of them.
+fn color_teardrop<S>(contour: &mut Vec<Edge<S>>, corner: usize, colors: [EdgeColor; 3])
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    let m = contour.len();
+    if m >= 3 {
+        for i in 0..m {
+            contour[(corner + i) % m].color = colors[symmetrical_trichotomy(i, m)];
+        }
+    } else if m == 1 {
+        let parts = contour[0].segment.split_in_thirds();
+        contour.clear();
+        for (part, color) in parts.iter().zip(colors.iter()) {
+            contour.push(Edge::new(*part, *color, false));
+        }
+    } else if m == 2 {
+        let first = contour[corner].segment.split_in_thirds();
+        let second = contour[1 - corner].segment.split_in_thirds();
+        contour.clear();
+        for (i, part) in first.iter().chain(second.iter()).enumerate() {
+            contour.push(Edge::new(*part, colors[i / 2], false));
+        }
+    }
+}
+
+/// A corner found by `Shape::color_edges_ink_trap`.
+struct InkTrapCorner<S> {
+    index: usize,
+    prev_edge_length_estimate: S,
+    minor: bool,
+    color: EdgeColor,
+}
+
+impl<S> Shape<S>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    /// Assigns colors to the edges of all contours, using the given `strategy`.
+    ///
+    /// See `color_edges_simple` for the meaning of `angle_threshold` and `seed`.
+    /// The same `seed` always produces the same coloring.
+    pub fn color_edges(&mut self, strategy: ColoringStrategy, angle_threshold: S, seed: u64) {
+        match strategy {
+            ColoringStrategy::Simple => self.color_edges_simple(angle_threshold, seed),
+            ColoringStrategy::InkTrap => self.color_edges_ink_trap(angle_threshold, seed),
+            ColoringStrategy::ByDistance => self.color_edges_by_distance(angle_threshold, seed),
+        }
+    }
+
+    /// Replaces each contour by the result of `f`, fixing up the contour
+    /// start flags afterwards.
+    pub(crate) fn map_contours<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Vec<Edge<S>>),
+    {
+        let ranges = self.contour_ranges();
+        let mut edges = core::mem::take(&mut self.edges).into_iter();
+        for range in ranges {
+            let mut contour: Vec<Edge<S>> = edges.by_ref().take(range.len()).collect();
+            f(&mut contour);
+            for (i, edge) in contour.iter_mut().enumerate() {
+                edge.is_new_contour = i == 0;
+            }
+            self.edges.append(&mut contour);
+        }
+        self.update_bounds();
+    }
+
+    /// Assigns colors to the edges of all contours, so that the channels
+    /// change at every corner.
+    ///
+    /// A corner is a point, where the direction of the contour changes by more
+    /// than `angle_threshold` (in radians, `3.0` is a good default). The `seed`
+    /// selects between the possible color sequences.
+    pub fn color_edges_simple(&mut self, angle_threshold: S, mut seed: u64) {
+        let cross_threshold = angle_threshold.sin();
+        let mut color = EdgeColor::WHITE;
+        self.map_contours(|contour| {
+            let corners = find_corners(contour, cross_threshold);
+            if corners.is_empty() {
+                // smooth contour
+                switch_color(&mut color, &mut seed, EdgeColor::BLACK);
+                for edge in contour.iter_mut() {
+                    edge.color = color;
+                }
+            } else if corners.len() == 1 {
+                // "teardrop"
+                switch_color(&mut color, &mut seed, EdgeColor::BLACK);
+                let first = color;
+                switch_color(&mut color, &mut seed, EdgeColor::BLACK);
+                color_teardrop(contour, corners[0], [first, EdgeColor::WHITE, color]);
+            } else {
+                // multiple corners
+                let corner_count = corners.len();
+                let start = corners[0];
+                let m = contour.len();
+                let mut spline = 0;
+                switch_color(&mut color, &mut seed, EdgeColor::BLACK);
+                let initial_color = color;
+                for i in 0..m {
+                    let index = (start + i) % m;
+                    if spline + 1 < corner_count && corners[spline + 1] == index {
+                        spline += 1;
+                        let banned = if spline == corner_count - 1 {
+                            initial_color
+                        } else {
+                            EdgeColor::BLACK
+                        };
+                        switch_color(&mut color, &mut seed, banned);
+                    }
+                    contour[index].color = color;
+                }
+            }
+        });
+    }
+
+    /// Assigns colors to the edges of all contours like `color_edges_simple`,
+    /// but treats corners, that enclose a span shorter than both its
+    /// neighbors, as minor corners.
+    ///
+    /// The span between minor corners gets the color shared by its neighbors,
+    /// which avoids artifacts in ink traps and tight serifs.
+    pub fn color_edges_ink_trap(&mut self, angle_threshold: S, mut seed: u64) {
+        let cross_threshold = angle_threshold.sin();
+        let mut color = EdgeColor::WHITE;
+        self.map_contours(|contour| {
+            let mut corners = Vec::new();
+            let mut spline_length = S::zero();
+            if let Some(last) = contour.last() {
+                let mut prev_direction = last.segment.direction(S::one());
+                for (index, edge) in contour.iter().enumerate() {
+                    if is_corner(
+                        prev_direction.normalized(),
+                        edge.segment.direction(S::zero()).normalized(),
+                        cross_threshold,
+                    ) {
+                        corners.push(InkTrapCorner {
+                            index,
+                            prev_edge_length_estimate: spline_length,
+                            minor: false,
+                            color: EdgeColor::BLACK,
+                        });
+                        spline_length = S::zero();
+                    }
+                    spline_length = spline_length + estimate_edge_length(&edge.segment);
+                    prev_direction = edge.segment.direction(S::one());
+                }
+            }
+
+            if corners.is_empty() {
+                // smooth contour
+                switch_color(&mut color, &mut seed, EdgeColor::BLACK);
+                for edge in contour.iter_mut() {
+                    edge.color = color;
+                }
+            } else if corners.len() == 1 {
+                // "teardrop"
+                switch_color(&mut color, &mut seed, EdgeColor::BLACK);
+                let first = color;
+                switch_color(&mut color, &mut seed, EdgeColor::BLACK);
+                color_teardrop(contour, corners[0].index, [first, EdgeColor::WHITE, color]);
+            } else {
+                // multiple corners
+                let corner_count = corners.len();
+                let mut major_corner_count = corner_count;
+                if corner_count > 3 {
+                    corners[0].prev_edge_length_estimate =
+                        corners[0].prev_edge_length_estimate + spline_length;
+                    for i in 0..corner_count {
+                        let next = corners[(i + 1) % corner_count].prev_edge_length_estimate;
+                        if corners[i].prev_edge_length_estimate > next
+                            && next < corners[(i + 2) % corner_count].prev_edge_length_estimate
+                        {
+                            corners[i].minor = true;
+                            major_corner_count -= 1;
+                        }
+                    }
+                }
+
+                let mut initial_color = EdgeColor::BLACK;
+                for corner in corners.iter_mut().filter(|corner| !corner.minor) {
+                    major_corner_count -= 1;
+                    let banned = if major_corner_count == 0 {
+                        initial_color
+                    } else {
+                        EdgeColor::BLACK
+                    };
+                    switch_color(&mut color, &mut seed, banned);
+                    corner.color = color;
+                    if initial_color == EdgeColor::BLACK {
+                        initial_color = color;
+                    }
+                }
+                for i in 0..corner_count {
+                    if corners[i].minor {
+                        let next_color = corners[(i + 1) % corner_count].color;
+                        corners[i].color = (color & next_color) ^ EdgeColor::WHITE;
+                    } else {
+                        color = corners[i].color;
+                    }
+                }
+
+                let start = corners[0].index;
+                let m = contour.len();
+                let mut spline = 0;
+                color = corners[0].color;
+                for i in 0..m {
+                    let index = (start + i) % m;
+                    if spline + 1 < corner_count && corners[spline + 1].index == index {
+                        spline += 1;
+                        color = corners[spline].color;
+                    }
+                    contour[index].color = color;
+                }
+            }
+        });
+    }
+
+    /// Assigns colors to the edges of all contours, so that splines (the
+    /// edges between two corners), which are close to each other, get
+    /// different colors whenever possible.
+    ///
+    /// Splines are added to a graph in order of their distance, which is then
+    /// colored with three colors. This reduces clashes between channels of
+    /// nearby edges, at the cost of a slower coloring.
+    pub fn color_edges_by_distance(&mut self, angle_threshold: S, mut seed: u64) {
+        let cross_threshold = angle_threshold.sin();
+
+        // split "teardrops" with less than three edges, so every spline has
+        // at least one edge of its own
+        self.map_contours(|contour| {
+            let corners = find_corners(contour, cross_threshold);
+            if corners.len() == 1 && contour.len() < 3 {
+                color_teardrop(contour, corners[0], [EdgeColor::WHITE; 3]);
+            }
+        });
+
+        // assign every edge to a spline
+        let mut splines = alloc::vec![0; self.edges.len()];
+        let mut spline_count = 0;
+        for range in self.contour_ranges() {
+            let contour = &self.edges[range.clone()];
+            let splines = &mut splines[range];
+            let corners = find_corners(contour, cross_threshold);
+            let m = contour.len();
+            if corners.is_empty() {
+                for spline in splines.iter_mut() {
+                    *spline = spline_count;
+                }
+                spline_count += 1;
+            } else if corners.len() == 1 {
+                for i in 0..m {
+                    splines[(corners[0] + i) % m] = spline_count + symmetrical_trichotomy(i, m);
+                }
+                spline_count += 3;
+            } else {
+                let mut spline = 0;
+                for i in 0..m {
+                    let index = (corners[0] + i) % m;
+                    if spline + 1 < corners.len() && corners[spline + 1] == index {
+                        spline += 1;
+                    }
+                    splines[index] = spline_count + spline;
+                }
+                spline_count += corners.len();
+            }
+        }
+        if spline_count == 0 {
+            return;
+        }
+
+        // compute the distances between all pairs of splines
+        let size = self.aabr().size();
+        let epsilon = max(
+            max(size.w, size.h) / <S as From<u16>>::from(4096),
+            S::epsilon() + S::epsilon(),
+        );
+        let mut distances = alloc::vec![S::max_value(); spline_count * spline_count];
+        for (k, a) in self.edges.iter().enumerate() {
+            for (l, b) in self.edges.iter().enumerate().skip(k + 1) {
+                let (i, j) = (splines[k], splines[l]);
+                if i != j {
+                    let distance = edge_to_edge_distance(&a.segment, &b.segment, epsilon);
+                    let index = min(i, j) * spline_count + max(i, j);
+                    distances[index] = min(distances[index], distance);
+                }
+            }
+        }
+        let mut graph_edges = Vec::new();
+        for i in 0..spline_count {
+            for j in (i + 1)..spline_count {
+                graph_edges.push((distances[i * spline_count + j], i, j));
+            }
+        }
+        graph_edges.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(core::cmp::Ordering::Equal));
+
+        // touching splines must have different colors, the others are added
+        // in ascending order of their distance as long as possible
+        let mut adjacency = alloc::vec![false; spline_count * spline_count];
+        let touching = graph_edges
+            .iter()
+            .take_while(|(distance, _, _)| *distance <= S::zero())
+            .count();
+        for &(_, i, j) in &graph_edges[..touching] {
+            adjacency[i * spline_count + j] = true;
+            adjacency[j * spline_count + i] = true;
+        }
+        let mut coloring = alloc::vec![0; spline_count];
+        color_second_degree_graph(&mut coloring, &adjacency, &mut seed);
+        for &(_, i, j) in &graph_edges[touching..] {
+            try_add_edge(&mut coloring, &mut adjacency, i, j);
+        }
+
+        const COLORS: [EdgeColor; 3] = [EdgeColor::YELLOW, EdgeColor::CYAN, EdgeColor::MAGENTA];
+        for (edge, spline) in self.edges.iter_mut().zip(splines) {
+            edge.color = COLORS[coloring[spline]];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svg::parse_path;
+
+    const SQUARE: &str = "M 0 0 L 0 10 L 10 10 L 10 0 Z";
+    const CIRCLE: &str = "M 1 0 C 1 0.55 0.55 1 0 1 C -0.55 1 -1 0.55 -1 0 \
+                          C -1 -0.55 -0.55 -1 0 -1 C 0.55 -1 1 -0.55 1 0 Z";
+
+    fn colors(path: &str, strategy: ColoringStrategy, seed: u64) -> Vec<EdgeColor> {
+        let mut shape = parse_path(path).unwrap();
+        shape.color_edges(strategy, 3.0, seed);
+        shape.edges().iter().map(|edge| edge.color).collect()
+    }
+
+    /// Whether the colors on both sides of each corner share at most one
+    /// channel and each edge has at least two.
+    fn is_valid_at_corners(colors: &[EdgeColor]) -> bool {
+        let channels = |color: EdgeColor| color.bits().count_ones();
+        let mut prev = colors[colors.len() - 1];
+        colors.iter().all(|&color| {
+            let valid = channels(color) >= 2 && channels(prev & color) <= 1;
+            prev = color;
+            valid
+        })
+    }
+
+    #[test]
+    fn square_corners() {
+        let shape = parse_path::<f64>(SQUARE).unwrap();
+        assert_eq!(find_corners(shape.edges(), 3.0f64.sin()), [0, 1, 2, 3]);
+        for seed in 0..16 {
+            let colors = colors(SQUARE, ColoringStrategy::Simple, seed);
+            assert!(is_valid_at_corners(&colors), "{:?}", colors);
+        }
+    }
+
+    #[test]
+    fn smooth_contour() {
+        let shape = parse_path::<f64>(CIRCLE).unwrap();
+        assert!(find_corners(shape.edges(), 3.0f64.sin()).is_empty());
+        for seed in 0..16 {
+            let colors = colors(CIRCLE, ColoringStrategy::Simple, seed);
+            assert_eq!(colors.len(), 4);
+            assert!(colors.iter().all(|&color| color == colors[0]));
+            assert!(colors[0].bits().count_ones() >= 2);
+        }
+    }
+
+    #[test]
+    fn single_edge_teardrop() {
+        let teardrop = "M 0 0 C 10 10 10 -10 0 0 Z";
+        let mut shape = parse_path::<f64>(teardrop).unwrap();
+        assert_eq!(shape.edges().len(), 1);
+        let curve = shape.edges()[0].segment;
+        shape.color_edges(ColoringStrategy::Simple, 3.0, 0);
+        let edges = shape.edges();
+        assert_eq!(edges.len(), 3);
+        assert!(edges[0].is_new_contour && !edges[1].is_new_contour);
+        assert_eq!(edges[0].segment.start(), curve.start());
+        assert_eq!(edges[0].segment.end(), edges[1].segment.start());
+        assert_eq!(edges[1].segment.end(), edges[2].segment.start());
+        assert_eq!(edges[2].segment.end(), curve.end());
+        let colors: Vec<_> = edges.iter().map(|edge| edge.color).collect();
+        assert_eq!(colors[1], EdgeColor::WHITE);
+        assert!(colors[0] != colors[2]);
+        assert!(is_valid_at_corners(&[colors[2], colors[0]]));
+    }
+
+    #[test]
+    fn seeds_are_deterministic() {
+        let glyph = "M 0 0 L 0 10 Q 5 12 10 10 L 10 0 C 7 2 3 2 0 0 Z \
+                     M 3 3 L 7 3 L 5 6 Z";
+        for &strategy in &[
+            ColoringStrategy::Simple,
+            ColoringStrategy::InkTrap,
+            ColoringStrategy::ByDistance,
+        ] {
+            let mut different = false;
+            for seed in 0..8 {
+                let a = colors(glyph, strategy, seed);
+                assert_eq!(a, colors(glyph, strategy, seed), "{:?}", strategy);
+                different |= a != colors(glyph, strategy, seed + 1);
+            }
+            assert!(different, "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn ink_traps_are_minor_corners() {
+        // a square with two of its corners cut off by short edges
+        let chamfered = "M 0 0 L 0 10 L 9.5 10 L 10 9.5 L 10 0.5 L 9.5 0 Z";
+        let square = colors(SQUARE, ColoringStrategy::InkTrap, 0);
+        for seed in 0..8 {
+            let square = colors(SQUARE, ColoringStrategy::InkTrap, seed);
+            let colors = colors(chamfered, ColoringStrategy::InkTrap, seed);
+            assert!(is_valid_at_corners(&colors), "{:?}", colors);
+            // the long edges are colored like the square, the short ones
+            // with the channels, which their neighbors don't share
+            let long = [colors[0], colors[1], colors[3], colors[5]];
+            assert_eq!(long, square[..], "{}", seed);
+            assert_eq!(colors[2], (colors[1] & colors[3]) ^ EdgeColor::WHITE);
+            assert_eq!(colors[4], (colors[3] & colors[5]) ^ EdgeColor::WHITE);
+        }
+        // every short edge is a spline of its own for the simple strategy
+        let colors = colors(chamfered, ColoringStrategy::Simple, 0);
+        assert!(is_valid_at_corners(&colors), "{:?}", colors);
+        assert!([colors[0], colors[1], colors[3], colors[5]] != square[..]);
+    }
+
+    #[test]
+    fn nearby_edges_of_different_contours() {
+        // the right edge of the square is close to the left edge of the
+        // rectangle, all other edges are farther apart
+        let squares = "M 0 0 L 0 10 L 10 10 L 10 0 Z M 10.5 2 L 10.5 8 L 20 8 L 20 2 Z";
+        let simple = colors(squares, ColoringStrategy::Simple, 0);
+        assert_eq!(simple[2], simple[4]);
+        for seed in 0..8 {
+            let colors = colors(squares, ColoringStrategy::ByDistance, seed);
+            assert!(colors[2] != colors[4], "{} {:?}", seed, colors);
+            assert!(is_valid_at_corners(&colors[..4]) && is_valid_at_corners(&colors[4..]));
+        }
+    }
+}
@@ -0,0 +1,477 @@
+//! Detection and correction of artifacts in multi-channel distance fields.
+//!
+//! Bilinear interpolation between two texels, where two channels change
+//! abruptly (a *clash*), can produce a median that crosses the edge in the
+//! wrong place. Such texels are repaired by replacing all channels with their
+//! median, which turns them into a plain (single channel) distance.
+
+use alloc::vec::Vec;
+use approx::RelativeEq;
+use num_traits::{real::Real, FloatConst, NumCast};
+use vek::Vec2;
+
+use crate::{
+    bitmap::{Bitmap, Channel},
+    math::{median, mix},
+    raster::{true_distance, DistanceMapping, Rasterizer},
+    shape::{EdgeColor, Shape},
+};
+
+/// Selects which texels are corrected.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorCorrectionMode {
+    /// Skips error correction.
+    Disabled,
+    /// Corrects all clashes, regardless if edges or corners are affected.
+    Indiscriminate,
+    /// Corrects all clashes, but texels at edges and corners only, when the
+    /// exact distance confirms the artifact.
+    EdgePriority,
+    /// Only corrects clashes at edges, texels at corners only, when the exact
+    /// distance confirms the artifact.
+    EdgeOnly,
+}
+
+/// Selects when the exact distance to the shape is used to confirm an
+/// artifact.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DistanceCheckMode {
+    /// Never computes the exact distance.
+    DoNotCheck,
+    /// Computes the exact distance only at edges.
+    CheckAtEdge,
+    /// Computes the exact distance for every suspected artifact.
+    AlwaysCheck,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ErrorCorrectionConfig<S> {
+    pub mode: ErrorCorrectionMode,
+    pub distance_check_mode: DistanceCheckMode,
+    /// The minimum difference between two neighboring texels (in pixels) to
+    /// be considered a discontinuity.
+    pub threshold: S,
+}
+
+impl<S: Real + From<u16>> Default for ErrorCorrectionConfig<S> {
+    fn default() -> Self {
+        ErrorCorrectionConfig {
+            mode: ErrorCorrectionMode::EdgePriority,
+            distance_check_mode: DistanceCheckMode::CheckAtEdge,
+            threshold: S::one() + S::one() / <S as From<u16>>::from(1000),
+        }
+    }
+}
+
+const EDGE: u8 = 1;
+const CORNER: u8 = 2;
+const ERROR: u8 = 4;
+
+/// Returns `true`, if `a` and `b` are clashing and `a` is the texel farther
+/// away from the edge.
+fn is_clash<S: Real>(a: [S; 3], b: [S; 3], threshold: S) -> bool {
+    let inside = |p: [S; 3]| p.iter().filter(|d| **d > S::zero()).count();
+    let (a_inside, b_inside) = (inside(a), inside(b));
+    // only consider texels on the same side of the edge, which are not
+    // completely inside or outside
+    if (a_inside >= 2) != (b_inside >= 2) || a_inside % 3 == 0 || b_inside % 3 == 0 {
+        return false;
+    }
+    // find the two channels that change their sign
+    let mut changing = (0..3).filter(|&i| (a[i] > S::zero()) != (b[i] > S::zero()));
+    let (first, second) = match (changing.next(), changing.next()) {
+        (Some(first), Some(second)) => (first, second),
+        _ => return false,
+    };
+    let other = 3 - first - second;
+    (a[first] - b[first]).abs() >= threshold
+        && (a[second] - b[second]).abs() >= threshold
+        && a[other].abs() >= b[other].abs()
+}
+
+impl<S> Rasterizer<S>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    /// Corrects clashing texels of a multi-channel distance field, which was
+    /// produced by `rasterize_msdf` (or `rasterize_mtsdf`) with the same
+    /// `shape`, `range` and `mapping`.
+    ///
+    /// Only the first three channels of `target` are considered.
+    pub fn correct_msdf_errors<B: Bitmap>(
+        &self,
+        shape: &Shape<S>,
+        range: S,
+        mapping: DistanceMapping,
+        target: &mut B,
+        config: &ErrorCorrectionConfig<S>,
+    ) {
+        let (width, height, channels) = (target.width(), target.height(), target.channels());
+        assert!(channels >= 3, "multi-channel distance field expected");
+        if config.mode == ErrorCorrectionMode::Disabled || width == 0 || height == 0 {
+            return;
+        }
+        let half = S::one() / (S::one() + S::one());
+        let shape = &self.project_shape(shape);
+        let combiner = self.combiner(shape);
+        let epsilon = self.epsilon();
+        // distances to the edge (in pixels), which are too small to confirm an artifact
+        let tolerance = S::one() / <S as From<u16>>::from(8);
+
+        // distances in pixels
+        let mut distances: Vec<[S; 3]> = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for pixel in target.row(y)[..width * channels].chunks_exact(channels) {
+                let distance = |i: usize| self.unmap_distance(pixel[i].to_value(), range, mapping);
+                distances.push([distance(0), distance(1), distance(2)]);
+            }
+        }
+        let is_inside = |i: usize| {
+            let [r, g, b] = distances[i];
+            median(r, g, b) > S::zero()
+        };
+        let neighbors = |i: usize| {
+            let (x, y) = (i % width, i / width);
+            let mut n = [None; 4];
+            if x > 0 {
+                n[0] = Some(i - 1);
+            }
+            if x + 1 < width {
+                n[1] = Some(i + 1);
+            }
+            if y > 0 {
+                n[2] = Some(i - width);
+            }
+            if y + 1 < height {
+                n[3] = Some(i + width);
+            }
+            n
+        };
+
+        let mut stencil = alloc::vec![0u8; width * height];
+        for (i, flags) in stencil.iter_mut().enumerate() {
+            if neighbors(i)
+                .iter()
+                .flatten()
+                .any(|&j| is_inside(i) != is_inside(j))
+            {
+                *flags |= EDGE;
+            }
+        }
+        if config.mode != ErrorCorrectionMode::Indiscriminate {
+            self.protect_corners(shape, &mut stencil, width, height);
+        }
+
+        let exact_check = |i: usize, j: usize| {
+            // the interpolated median halfway between both texels has to be
+            // on the same side of the edge as the shape
+            let (a, b) = (distances[i], distances[j]);
+            let interpolated = median(
+                mix(a[0], b[0], half),
+                mix(a[1], b[1], half),
+                mix(a[2], b[2], half),
+            );
+            let (x_i, y_i) = (i % width, i / width);
+            let (x_j, y_j) = (j % width, j / width);
+            let x: S = NumCast::from(x_i + x_j + 1).unwrap();
+            let y: S = NumCast::from(self.row(y_i, height) + self.row(y_j, height) + 1).unwrap();
+            let point = Vec2::new(x, y) * half;
+            let filled = self.is_filled(&shape.scanline(point.y), point.x);
+            let exact = true_distance(shape, combiner.as_ref(), point, range, epsilon);
+            (interpolated > S::zero()) != filled && exact > tolerance
+        };
+
+        for i in 0..width * height {
+            let flags = stencil[i];
+            let at_edge = flags & EDGE != 0;
+            let protected = match config.mode {
+                ErrorCorrectionMode::EdgePriority => flags & (EDGE | CORNER) != 0,
+                ErrorCorrectionMode::EdgeOnly => flags & CORNER != 0,
+                _ => false,
+            };
+            if (config.mode == ErrorCorrectionMode::EdgeOnly && !at_edge)
+                || (protected && config.distance_check_mode == DistanceCheckMode::DoNotCheck)
+            {
+                continue;
+            }
+            let check = protected
+                || match config.distance_check_mode {
+                    DistanceCheckMode::DoNotCheck => false,
+                    DistanceCheckMode::CheckAtEdge => at_edge,
+                    DistanceCheckMode::AlwaysCheck => true,
+                };
+            let is_error = neighbors(i).iter().flatten().any(|&j| {
+                is_clash(distances[i], distances[j], config.threshold)
+                    && (!check || exact_check(i, j))
+            });
+            if is_error {
+                stencil[i] |= ERROR;
+            }
+        }
+
+        for y in 0..height {
+            let flags = &stencil[y * width..(y + 1) * width];
+            let row = &mut target.row_mut(y)[..width * channels];
+            for (pixel, flags) in row.chunks_exact_mut(channels).zip(flags) {
+                if flags & ERROR != 0 {
+                    let value = median(pixel[0], pixel[1], pixel[2]);
+                    pixel[..3].copy_from_slice(&[value; 3]);
+                }
+            }
+        }
+    }
+
+    /// Marks the texels around corners of the `projected` shape, where the
+    /// colors of the adjacent edges share at most one channel.
+    fn protect_corners(
+        &self,
+        projected: &Shape<S>,
+        stencil: &mut [u8],
+        width: usize,
+        height: usize,
+    ) {
+        for range in projected.contour_ranges() {
+            let contour = &projected.edges[range];
+            let mut prev_color = contour.last().map_or(EdgeColor::BLACK, |edge| edge.color);
+            for edge in contour {
+                let common = (prev_color & edge.color).bits();
+                prev_color = edge.color;
+                if common & common.wrapping_sub(1) != 0 {
+                    continue;
+                }
+                let half = S::one() / (S::one() + S::one());
+                let corner = edge.segment.start() - Vec2::new(half, half);
+                let (l, b) = (corner.x.floor(), corner.y.floor());
+                for &(dx, dy) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let x = l + <S as From<u16>>::from(dx);
+                    let y = b + <S as From<u16>>::from(dy);
+                    // also skips corners, which are not finite
+                    let (x, y) = match (x.to_usize(), y.to_usize()) {
+                        (Some(x), Some(y)) => (x, y),
+                        _ => continue,
+                    };
+                    if x < width && y < height {
+                        stencil[self.row(y, height) * width + x] |= CORNER;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::bitmap::BitmapBuf;
+    use crate::raster::YAxis;
+    use crate::shape::ColoringStrategy;
+    use crate::svg::parse_path;
+
+    const RANGE: f64 = 2.0;
+
+    /// A square with a triangular hole, whose sharp corners clash.
+    const HOLE: &str = "M 0 0 L 0 10 L 10 10 L 10 0 Z M 4 2 L 4 8 L 6 5 Z";
+    /// A thin arrow with a sharp tip.
+    const ARROW: &str = "M 0 0 L 10 1 L 0 2 L 3 1 Z";
+
+    struct Field {
+        rasterizer: Rasterizer<f64>,
+        shape: Shape<f64>,
+        bitmap: BitmapBuf<f32>,
+    }
+
+    impl Field {
+        fn new(path: &str) -> Self {
+            let mut shape = parse_path(path).unwrap();
+            shape.color_edges(ColoringStrategy::Simple, 3.0, 0);
+            let rasterizer = Rasterizer::new()
+                .with_scale(2.0)
+                .with_translate(2.0, 2.0)
+                .with_y_axis(YAxis::Down);
+            let mut bitmap = BitmapBuf::new(28, 28, 3);
+            rasterizer.rasterize_msdf(&shape, RANGE, DistanceMapping::Pixels, &mut bitmap);
+            Field {
+                rasterizer,
+                shape,
+                bitmap,
+            }
+        }
+
+        fn corrected(&self, mode: ErrorCorrectionMode, check: DistanceCheckMode) -> BitmapBuf<f32> {
+            let mut bitmap = self.bitmap.clone();
+            let config = ErrorCorrectionConfig {
+                mode,
+                distance_check_mode: check,
+                ..ErrorCorrectionConfig::default()
+            };
+            let mapping = DistanceMapping::Pixels;
+            self.rasterizer
+                .correct_msdf_errors(&self.shape, RANGE, mapping, &mut bitmap, &config);
+            bitmap
+        }
+
+        /// The indices of the texels, which differ from the uncorrected field.
+        fn changes(&self, corrected: &BitmapBuf<f32>) -> Vec<usize> {
+            let texels = self.bitmap.as_slice().chunks_exact(3);
+            let corrected = corrected.as_slice().chunks_exact(3);
+            (texels.zip(corrected).enumerate())
+                .filter(|(_, (a, b))| a != b)
+                .map(|(i, _)| i)
+                .collect()
+        }
+
+        /// The number of pairs of neighboring texels, whose interpolated
+        /// median is on the wrong side of the edge, not counting the pairs
+        /// right at the edge.
+        fn artifacts(&self, bitmap: &BitmapBuf<f32>) -> usize {
+            let shape = self.rasterizer.project_shape(&self.shape);
+            let mut artifacts = 0;
+            for y in 0..27 {
+                for x in 0..27 {
+                    for &(dx, dy) in &[(1, 0), (0, 1)] {
+                        let (a, b) = (bitmap.pixel(x, y), bitmap.pixel(x + dx, y + dy));
+                        let mid = |i: usize| (a[i] + b[i]) / 2.0;
+                        let inside = median(mid(0), mid(1), mid(2)) > 0.0;
+                        let x = x as f64 + 0.5 + dx as f64 / 2.0;
+                        let y = y as f64 + 0.5 + dy as f64 / 2.0;
+                        let filled = self.rasterizer.is_filled(&shape.scanline(y), x);
+                        let point = Vec2::new(x, y);
+                        let exact = true_distance(&shape, None, point, RANGE, 1e-6);
+                        if inside != filled && exact > 0.125 {
+                            artifacts += 1;
+                        }
+                    }
+                }
+            }
+            artifacts
+        }
+
+        /// The texels, whose median is on the other side of the edge than
+        /// the median of a neighbor.
+        fn edge_texels(&self) -> Vec<usize> {
+            let inside = |x: usize, y: usize| {
+                let p = self.bitmap.pixel(x, y);
+                median(p[0], p[1], p[2]) > 0.0
+            };
+            (0..28 * 28usize)
+                .filter(|&i| {
+                    let (x, y) = (i % 28, i / 28);
+                    let neighbors = [
+                        (x.wrapping_sub(1), y),
+                        (x + 1, y),
+                        (x, y.wrapping_sub(1)),
+                        (x, y + 1),
+                    ];
+                    neighbors
+                        .iter()
+                        .any(|&(nx, ny)| nx < 28 && ny < 28 && inside(nx, ny) != inside(x, y))
+                })
+                .collect()
+        }
+
+        /// The four texels around every corner of the shape.
+        fn corner_texels(&self) -> Vec<usize> {
+            let shape = self.rasterizer.project_shape(&self.shape);
+            let mut texels = Vec::new();
+            for edge in shape.edges() {
+                let corner = edge.segment.start() - Vec2::new(0.5, 0.5);
+                let (x, y) = (corner.x.floor() as usize, corner.y.floor() as usize);
+                texels.extend_from_slice(&[
+                    y * 28 + x,
+                    y * 28 + x + 1,
+                    (y + 1) * 28 + x,
+                    (y + 1) * 28 + x + 1,
+                ]);
+            }
+            texels
+        }
+    }
+
+    const CHECKS: [DistanceCheckMode; 3] = [
+        DistanceCheckMode::DoNotCheck,
+        DistanceCheckMode::CheckAtEdge,
+        DistanceCheckMode::AlwaysCheck,
+    ];
+
+    #[test]
+    fn clashes_are_repaired() {
+        let field = Field::new(HOLE);
+        assert!(field.artifacts(&field.bitmap) > 10);
+        for &check in &CHECKS {
+            for &mode in &[
+                ErrorCorrectionMode::Indiscriminate,
+                ErrorCorrectionMode::EdgePriority,
+            ] {
+                let corrected = field.corrected(mode, check);
+                assert_eq!(field.artifacts(&corrected), 0, "{:?} {:?}", mode, check);
+            }
+            let corrected = field.corrected(ErrorCorrectionMode::Disabled, check);
+            assert_eq!(corrected, field.bitmap, "{:?}", check);
+        }
+    }
+
+    #[test]
+    fn edge_only_changes_edge_texels() {
+        let field = Field::new(ARROW);
+        let edges = field.edge_texels();
+        let mut changed = 0;
+        for &check in &CHECKS {
+            let corrected = field.corrected(ErrorCorrectionMode::EdgeOnly, check);
+            for i in field.changes(&corrected) {
+                assert!(edges.contains(&i), "{} {:?}", i, check);
+                changed += 1;
+            }
+        }
+        assert!(changed > 0);
+    }
+
+    #[test]
+    fn corners_are_protected() {
+        let mut field = Field::new(ARROW);
+        let (edges, corners) = (field.edge_texels(), field.corner_texels());
+        // a clash of a texel at the tip of the arrow with its right neighbor
+        let (tip, neighbor) = (5 * 28 + 24, 5 * 28 + 25);
+        assert!(corners.contains(&tip) && !corners.contains(&neighbor));
+        let data = field.bitmap.as_mut_slice();
+        data[tip * 3..tip * 3 + 3].copy_from_slice(&[-1.5, 1.5, 0.3]);
+        data[neighbor * 3..neighbor * 3 + 3].copy_from_slice(&[1.5, -1.5, 0.2]);
+
+        let check = DistanceCheckMode::DoNotCheck;
+        let corrected = field.corrected(ErrorCorrectionMode::Indiscriminate, check);
+        assert!(field.changes(&corrected).contains(&tip));
+        let corrected = field.corrected(ErrorCorrectionMode::EdgePriority, check);
+        let changes = field.changes(&corrected);
+        assert!(!changes.is_empty());
+        for i in changes {
+            assert!(!edges.contains(&i) && !corners.contains(&i), "{}", i);
+        }
+        let corrected = field.corrected(ErrorCorrectionMode::EdgeOnly, check);
+        for i in field.changes(&corrected) {
+            assert!(!corners.contains(&i), "{}", i);
+        }
+    }
+
+    #[test]
+    fn distance_check_confirms_artifacts() {
+        let field = Field::new(ARROW);
+        let mode = ErrorCorrectionMode::Indiscriminate;
+        let unchecked = field.changes(&field.corrected(mode, DistanceCheckMode::DoNotCheck));
+        let checked = field.changes(&field.corrected(mode, DistanceCheckMode::AlwaysCheck));
+        assert!(!checked.is_empty() && checked.len() < unchecked.len());
+        assert!(checked.iter().all(|i| unchecked.contains(i)));
+    }
+
+    #[test]
+    fn non_finite_corners() {
+        let field = Field::new(ARROW);
+        let config = ErrorCorrectionConfig::default();
+        for &scale in &[1e300, f64::NAN] {
+            let rasterizer = Rasterizer::new().with_scale(scale);
+            let mut bitmap = field.bitmap.clone();
+            let mapping = DistanceMapping::Pixels;
+            rasterizer.correct_msdf_errors(&field.shape, RANGE, mapping, &mut bitmap, &config);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Fitting shapes into bitmaps.
+//!
+//! A `Framing` holds the options (padding, aspect ratio and snapping) and
+//! computes a `Frame`: the size of the bitmap and the scale and translation
+//! of the shape, which can be passed on to `Rasterizer::with_frame`.
+
+use approx::RelativeEq;
+use num_traits::{real::Real, FloatConst, NumCast};
+use vek::{Aabr, Vec2};
+
+use crate::{math::max, raster::Rasterizer};
+
+/// The size of a bitmap and the placement of a shape in it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frame<S> {
+    /// The width of the bitmap in pixels.
+    pub width: usize,
+    /// The height of the bitmap in pixels.
+    pub height: usize,
+    /// The scale from shape units to pixels.
+    pub scale: Vec2<S>,
+    /// The translation in shape units, which is applied before scaling.
+    pub translate: Vec2<S>,
+}
+
+impl<S: Real> Frame<S> {
+    /// Returns the area, which is covered by the bitmap, in shape units.
+    pub fn bounds(&self) -> Aabr<S> {
+        let to_s = |value: usize| -> S { NumCast::from(value).unwrap() };
+        let min = -self.translate;
+        Aabr {
+            min,
+            max: min + Vec2::new(to_s(self.width), to_s(self.height)) / self.scale,
+        }
+    }
+}
+
+/// Options for fitting shapes into bitmaps.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Framing<S> {
+    padding: S,
+    preserve_aspect_ratio: bool,
+    snap: bool,
+}
+
+impl<S: Real> Framing<S> {
+    /// Creates a framing, which leaves `padding` pixels on each side of the
+    /// shape (usually the distance range).
+    pub fn new(padding: S) -> Self {
+        Framing {
+            padding,
+            preserve_aspect_ratio: true,
+            snap: false,
+        }
+    }
+
+    /// Whether `fit` scales both axes by the same factor (the default).
+    pub fn with_aspect_ratio(mut self, preserve: bool) -> Self {
+        self.preserve_aspect_ratio = preserve;
+        self
+    }
+
+    /// Whether the origin of the shape is placed on a pixel corner (off by
+    /// default), so glyphs rendered at the same size share their baseline.
+    pub fn with_pixel_snapping(mut self, snap: bool) -> Self {
+        self.snap = snap;
+        self
+    }
+
+    /// Snaps the translation, so the origin lands on a pixel corner.
+    fn snapped(&self, scale: Vec2<S>, translate: Vec2<S>) -> Vec2<S> {
+        if self.snap {
+            (translate * scale).round() / scale
+        } else {
+            translate
+        }
+    }
+
+    /// Scales `bounds` (e.g. the `aabr` of a shape) to fit into a bitmap of
+    /// `width` x `height` pixels (minus the padding) and centers it.
+    ///
+    /// Returns `None`, if the padding doesn't leave any space, or if `bounds`
+    /// has no width or no height.
+    pub fn fit(&self, bounds: Aabr<S>, width: usize, height: usize) -> Option<Frame<S>> {
+        let _2 = S::one() + S::one();
+        let size = Vec2::new(NumCast::from(width)?, NumCast::from(height)?);
+        let space = size - Vec2::broadcast(self.padding * _2);
+        let extent = bounds.max - bounds.min;
+        // written to be false for NaNs, too
+        let is_positive = |value: S| value > S::zero() && value <= S::max_value();
+        if !(is_positive(space.x)
+            && is_positive(space.y)
+            && is_positive(extent.x)
+            && is_positive(extent.y))
+        {
+            return None;
+        }
+        let scale = space / extent;
+        let scale = if self.preserve_aspect_ratio {
+            Vec2::broadcast(scale.x.min(scale.y))
+        } else {
+            scale
+        };
+        Some(self.center(bounds, width, height, scale))
+    }
+
+    /// Centers `bounds` at the given `scale` in a bitmap of `width` x
+    /// `height` pixels, regardless whether it fits.
+    pub fn center(&self, bounds: Aabr<S>, width: usize, height: usize, scale: Vec2<S>) -> Frame<S> {
+        let _2 = S::one() + S::one();
+        let size = Vec2::new(
+            NumCast::from(width).unwrap(),
+            NumCast::from(height).unwrap(),
+        );
+        let extent = bounds.max - bounds.min;
+        let translate = (size / scale - extent) / _2 - bounds.min;
+        Frame {
+            width,
+            height,
+            scale,
+            translate: self.snapped(scale, translate),
+        }
+    }
+
+    /// Chooses the size of the bitmap, which fits `bounds` at the given
+    /// `scale` plus the padding.
+    pub fn scaled(&self, bounds: Aabr<S>, scale: S) -> Frame<S> {
+        let scale = Vec2::new(scale, scale);
+        let start = bounds.min * scale - Vec2::broadcast(self.padding);
+        let end = bounds.max * scale + Vec2::broadcast(self.padding);
+        let (start, end) = if self.snap {
+            (start.floor(), end.ceil())
+        } else {
+            (start, start + (end - start).ceil())
+        };
+        let size = |extent: S| -> usize { NumCast::from(max(S::zero(), extent)).unwrap() };
+        Frame {
+            width: size(end.x - start.x),
+            height: size(end.y - start.y),
+            scale,
+            translate: -start / scale,
+        }
+    }
+
+    /// Like `scaled`, with the scale of a font with `units_per_em`, which is
+    /// rendered with `em_size` pixels per em.
+    pub fn at_em_size(&self, bounds: Aabr<S>, units_per_em: S, em_size: S) -> Frame<S> {
+        self.scaled(bounds, em_size / units_per_em)
+    }
+}
+
+impl<S> Rasterizer<S>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    /// Sets the scale and translation of `frame`.
+    pub fn with_frame(self, frame: &Frame<S>) -> Self {
+        self.with_scale2(frame.scale.x, frame.scale.y)
+            .with_translate(frame.translate.x, frame.translate.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds() -> Aabr<f64> {
+        Aabr {
+            min: Vec2::new(10.0, -20.0),
+            max: Vec2::new(30.0, 20.0),
+        }
+    }
+
+    /// The bounds in the pixels of `frame`.
+    fn projected(frame: &Frame<f64>, bounds: Aabr<f64>) -> Aabr<f64> {
+        let rasterizer = Rasterizer::new().with_frame(frame);
+        Aabr {
+            min: rasterizer.project(bounds.min),
+            max: rasterizer.project(bounds.max),
+        }
+    }
+
+    fn assert_close(a: Vec2<f64>, b: Vec2<f64>) {
+        assert!(
+            (a - b).map(Real::abs).reduce_partial_max() < 1e-9,
+            "{} != {}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn fit_preserving_the_aspect_ratio() {
+        let frame = Framing::new(4.0).fit(bounds(), 100, 100).unwrap();
+        assert_eq!((frame.width, frame.height), (100, 100));
+        assert_eq!(frame.scale, Vec2::broadcast(2.3));
+        let pixels = projected(&frame, bounds());
+        assert_close(pixels.min, Vec2::new(27.0, 4.0));
+        assert_close(pixels.max, Vec2::new(73.0, 96.0));
+        assert_close(
+            frame.bounds().min,
+            Vec2::new(20.0 - 50.0 / 2.3, -50.0 / 2.3),
+        );
+    }
+
+    #[test]
+    fn fit_stretched() {
+        let framing = Framing::new(4.0).with_aspect_ratio(false);
+        let frame = framing.fit(bounds(), 100, 50).unwrap();
+        assert_close(frame.scale, Vec2::new(4.6, 1.05));
+        let pixels = projected(&frame, bounds());
+        assert_close(pixels.min, Vec2::new(4.0, 4.0));
+        assert_close(pixels.max, Vec2::new(96.0, 46.0));
+    }
+
+    #[test]
+    fn pixel_snapping() {
+        let framing = Framing::new(4.0).with_pixel_snapping(true);
+        let origin =
+            |frame: &Frame<f64>| projected(frame, bounds()).min - bounds().min * frame.scale;
+        for &(width, height) in &[(100, 100), (37, 91), (64, 33)] {
+            let frame = framing.fit(bounds(), width, height).unwrap();
+            assert_close(origin(&frame), origin(&frame).round());
+            let pixels = projected(&frame, bounds());
+            // moved by at most half a pixel
+            assert!(pixels.min.x >= 3.5 && pixels.min.y >= 3.5, "{:?}", pixels);
+        }
+        for &scale in &[0.3, 1.0, 1.7] {
+            let frame = framing.scaled(bounds(), scale);
+            assert_close(origin(&frame), origin(&frame).round());
+            let pixels = projected(&frame, bounds());
+            assert!(pixels.min.x >= 4.0 && pixels.min.y >= 4.0, "{:?}", pixels);
+            assert!(pixels.max.x <= frame.width as f64 - 4.0, "{:?}", pixels);
+            assert!(pixels.max.y <= frame.height as f64 - 4.0, "{:?}", pixels);
+        }
+    }
+
+    #[test]
+    fn scaled_at_em_size() {
+        let framing = Framing::new(2.0);
+        // 32 pixels per em of 2048 units
+        let frame = framing.at_em_size(bounds(), 2048.0, 32.0);
+        assert_eq!(frame, framing.scaled(bounds(), 1.0 / 64.0));
+        assert_eq!(frame.scale, Vec2::broadcast(1.0 / 64.0));
+        // 20 x 40 units plus the padding
+        assert_eq!((frame.width, frame.height), (5, 5));
+        let pixels = projected(&frame, bounds());
+        assert_close(pixels.min, Vec2::new(2.0, 2.0));
+        assert_close(pixels.max, Vec2::new(2.3125, 2.625));
+
+        let frame = framing.at_em_size(bounds(), 1000.0, 100.0);
+        assert_eq!((frame.width, frame.height), (6, 8));
+    }
+
+    #[test]
+    fn degenerate_bounds() {
+        let framing = Framing::new(2.0);
+        let line = |min: Vec2<f64>, max: Vec2<f64>| Aabr { min, max };
+        let vertical = line(Vec2::new(5.0, 0.0), Vec2::new(5.0, 10.0));
+        let horizontal = line(Vec2::new(0.0, 5.0), Vec2::new(10.0, 5.0));
+        let point = line(Vec2::new(5.0, 5.0), Vec2::new(5.0, 5.0));
+        let nan = line(Vec2::new(f64::NAN, 0.0), Vec2::new(10.0, 10.0));
+        let infinite = line(Vec2::new(0.0, 0.0), Vec2::new(f64::INFINITY, 10.0));
+        for &bounds in &[vertical, horizontal, point, nan, infinite] {
+            assert_eq!(framing.fit(bounds, 20, 20), None, "{:?}", bounds);
+            let frame = framing.with_aspect_ratio(false).fit(bounds, 20, 20);
+            assert_eq!(frame, None, "{:?}", bounds);
+        }
+        // no space left by the padding
+        assert_eq!(framing.fit(bounds(), 4, 20), None);
+        assert_eq!(Framing::new(f64::NAN).fit(bounds(), 20, 20), None);
+
+        let frame = framing.scaled(point, 1.0);
+        assert_eq!((frame.width, frame.height), (4, 4));
+        assert!(frame.translate.x.is_finite() && frame.translate.y.is_finite());
+    }
+}
@@ -0,0 +1,42 @@
+use core::ops::{Deref, DerefMut};
+use image::{ImageBuffer, Pixel};
+
+use crate::bitmap::{Bitmap, Channel};
+
+impl<P, Container> Bitmap for ImageBuffer<P, Container>
+where
+    P: Pixel + 'static,
+    P::Subpixel: Channel + 'static,
+    Container: Deref<Target = [P::Subpixel]> + DerefMut,
+{
+    type Channel = P::Subpixel;
+
+    #[inline]
+    fn width(&self) -> usize {
+        ImageBuffer::width(self) as usize
+    }
+    #[inline]
+    fn height(&self) -> usize {
+        ImageBuffer::height(self) as usize
+    }
+    #[inline]
+    fn channels(&self) -> usize {
+        P::CHANNEL_COUNT as usize
+    }
+    #[inline]
+    fn row(&self, y: usize) -> &[P::Subpixel] {
+        let stride = Bitmap::width(self) * Bitmap::channels(self);
+        &Deref::deref(self)[y * stride..(y + 1) * stride]
+    }
+    #[inline]
+    fn row_mut(&mut self, y: usize) -> &mut [P::Subpixel] {
+        let stride = Bitmap::width(self) * Bitmap::channels(self);
+        &mut DerefMut::deref_mut(self)[y * stride..(y + 1) * stride]
+    }
+    #[inline]
+    fn rows_mut(&mut self) -> Option<(&mut [P::Subpixel], usize)> {
+        let stride = Bitmap::width(self) * Bitmap::channels(self);
+        let len = stride * Bitmap::height(self);
+        Some((&mut DerefMut::deref_mut(self)[..len], stride))
+    }
+}
@@ -0,0 +1,226 @@
+//! Export of atlas metadata in the JSON layout of msdf-atlas-gen.
+//!
+//! Plane bounds, advances and metrics are in ems, atlas bounds in pixels
+//! with the origin at the bottom-left corner of the atlas. Both bounds are
+//! inset by half a pixel, so the sampled area stays inside the cell.
+
+use core::fmt::{self, Write};
+use num_traits::real::Real;
+
+use crate::{
+    atlas::AtlasLayout,
+    metrics::{FontMetrics, KerningPair},
+    raster::FieldType,
+};
+
+/// Writes `value` as JSON number.
+fn number<W: Write, S: Real>(out: &mut W, value: S) -> fmt::Result {
+    let value = value.to_f64().unwrap_or(0.0);
+    if value.is_finite() {
+        write!(out, "{}", value)
+    } else {
+        out.write_char('0')
+    }
+}
+
+/// Writes a JSON object with the `left`, `bottom`, `right` and `top` keys.
+fn bounds<W: Write, S: Real>(out: &mut W, l: S, b: S, r: S, t: S) -> fmt::Result {
+    for (i, (key, value)) in [("left", l), ("bottom", b), ("right", r), ("top", t)]
+        .iter()
+        .enumerate()
+    {
+        out.write_str(if i == 0 { "{" } else { "," })?;
+        write!(out, "\"{}\":", key)?;
+        number(out, *value)?;
+    }
+    out.write_char('}')
+}
+
+fn type_name(field_type: FieldType) -> &'static str {
+    match field_type {
+        FieldType::Bitmap => "hardmask",
+        FieldType::Sdf => "sdf",
+        FieldType::Psdf => "psdf",
+        FieldType::Msdf => "msdf",
+        FieldType::Mtsdf => "mtsdf",
+    }
+}
+
+/// Writes the metadata of an atlas of `field_type` with the given `layout`.
+///
+/// Glyphs are identified by their `unicode` codepoint, or by their `index`
+/// (the glyph id or the index in the layout) if they have none.
+pub fn write_json<W: Write, S: Real>(
+    out: &mut W,
+    layout: &AtlasLayout<S>,
+    field_type: FieldType,
+    metrics: &FontMetrics<S>,
+    kerning: &[KerningPair<S>],
+) -> fmt::Result {
+    let em = metrics.em_size;
+    let to_s = |value: usize| -> S { S::from(value).unwrap() };
+    let half = S::one() / (S::one() + S::one());
+
+    write!(out, "{{\"atlas\":{{\"type\":\"{}\"", type_name(field_type))?;
+    out.write_str(",\"distanceRange\":")?;
+    number(out, layout.range)?;
+    out.write_str(",\"size\":")?;
+    number(out, layout.scale * em)?;
+    write!(
+        out,
+        ",\"width\":{},\"height\":{},\"yOrigin\":\"bottom\"}}",
+        layout.width, layout.height
+    )?;
+
+    out.write_str(",\"metrics\":{\"emSize\":1")?;
+    for (key, value) in &[
+        ("lineHeight", metrics.line_height),
+        ("ascender", metrics.ascender),
+        ("descender", metrics.descender),
+        ("underlineY", metrics.underline_y),
+        ("underlineThickness", metrics.underline_thickness),
+    ] {
+        write!(out, ",\"{}\":", key)?;
+        number(out, *value / em)?;
+    }
+    out.write_char('}')?;
+
+    let id = |index: usize| match (
+        layout.glyphs[index].codepoint,
+        layout.glyphs[index].glyph_id,
+    ) {
+        (Some(c), _) => ("unicode", c as u32),
+        (None, Some(glyph_id)) => ("index", glyph_id as u32),
+        (None, None) => ("index", index as u32),
+    };
+
+    out.write_str(",\"glyphs\":[")?;
+    for (index, glyph) in layout.glyphs.iter().enumerate() {
+        if index > 0 {
+            out.write_char(',')?;
+        }
+        let (key, value) = id(index);
+        write!(out, "{{\"{}\":{},\"advance\":", key, value)?;
+        number(out, glyph.advance / em)?;
+        if let Some(placement) = &glyph.placement {
+            let inset = half / layout.scale;
+            let (min, max) = (placement.bounds.min, placement.bounds.max);
+            out.write_str(",\"planeBounds\":")?;
+            bounds(
+                out,
+                (min.x + inset) / em,
+                (min.y + inset) / em,
+                (max.x - inset) / em,
+                (max.y - inset) / em,
+            )?;
+            let bottom = layout.height - placement.y - placement.height;
+            out.write_str(",\"atlasBounds\":")?;
+            bounds(
+                out,
+                to_s(placement.x) + half,
+                to_s(bottom) + half,
+                to_s(placement.x + placement.width) - half,
+                to_s(bottom + placement.height) - half,
+            )?;
+        }
+        out.write_char('}')?;
+    }
+
+    out.write_str("],\"kerning\":[")?;
+    for (i, pair) in kerning.iter().enumerate() {
+        if i > 0 {
+            out.write_char(',')?;
+        }
+        let (key1, value1) = id(pair.first);
+        let (key2, value2) = id(pair.second);
+        write!(
+            out,
+            "{{\"{}1\":{},\"{}2\":{},\"advance\":",
+            key1, value1, key2, value2
+        )?;
+        number(out, pair.advance / em)?;
+        out.write_char('}')?;
+    }
+    out.write_str("]}")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use vek::{Aabr, Vec2};
+
+    use super::*;
+    use crate::atlas::{AtlasGlyph, Placement};
+
+    #[test]
+    fn layout_of_msdf_atlas_gen() {
+        let placement = |x, y, size, min: (f64, f64), max: (f64, f64)| {
+            Some(Placement {
+                x,
+                y,
+                width: size,
+                height: size + 2,
+                bounds: Aabr {
+                    min: Vec2::new(min.0, min.1),
+                    max: Vec2::new(max.0, max.1),
+                },
+            })
+        };
+        let layout = AtlasLayout {
+            width: 64,
+            height: 32,
+            scale: 0.5,
+            range: 4.0,
+            glyphs: alloc::vec![
+                AtlasGlyph {
+                    glyph_id: Some(36),
+                    codepoint: Some('A'),
+                    advance: 20.0,
+                    placement: placement(1, 2, 10, (-2.0, -4.0), (18.0, 20.0)),
+                },
+                AtlasGlyph {
+                    glyph_id: Some(3),
+                    codepoint: Some(' '),
+                    advance: 8.0,
+                    placement: None,
+                },
+                AtlasGlyph {
+                    glyph_id: Some(7),
+                    codepoint: None,
+                    advance: 10.0,
+                    placement: placement(12, 0, 6, (0.0, 0.0), (8.0, 8.0)),
+                },
+            ],
+        };
+        let metrics = FontMetrics {
+            em_size: 32.0,
+            line_height: 40.0,
+            ascender: 28.0,
+            descender: -8.0,
+            underline_y: -2.0,
+            underline_thickness: 1.0,
+        };
+        let kerning = [KerningPair {
+            first: 0,
+            second: 2,
+            advance: -3.0,
+        }];
+        let mut out = String::new();
+        write_json(&mut out, &layout, FieldType::Msdf, &metrics, &kerning).unwrap();
+        let expected = concat!(
+            r#"{"atlas":{"type":"msdf","distanceRange":4,"size":16,"width":64,"height":32,"#,
+            r#""yOrigin":"bottom"},"#,
+            r#""metrics":{"emSize":1,"lineHeight":1.25,"ascender":0.875,"descender":-0.25,"#,
+            r#""underlineY":-0.0625,"underlineThickness":0.03125},"#,
+            r#""glyphs":[{"unicode":65,"advance":0.625,"#,
+            r#""planeBounds":{"left":-0.03125,"bottom":-0.09375,"right":0.53125,"top":0.59375},"#,
+            r#""atlasBounds":{"left":1.5,"bottom":18.5,"right":10.5,"top":29.5}},"#,
+            r#"{"unicode":32,"advance":0.25},"#,
+            r#"{"index":7,"advance":0.3125,"#,
+            r#""planeBounds":{"left":0.03125,"bottom":0.03125,"right":0.21875,"top":0.21875},"#,
+            r#""atlasBounds":{"left":12.5,"bottom":24.5,"right":17.5,"top":31.5}}],"#,
+            r#""kerning":[{"unicode1":65,"index2":7,"advance":-0.09375}]}"#,
+        );
+        assert_eq!(out, expected);
+    }
+}
@@ -1,15 +1,32 @@
 //! `msf` is a *Multi-channel signed distance field generator* for fonts.
 //!
-//! This crate is #![no_std]-compatibne, when the "no-std" feature is activated,
-//! but still requires the alloc crate.
+//! This crate is `#![no_std]`, unless the "std" feature is activated (it isn't
+//! by default), but still requires the alloc crate.
 
-#![cfg_attr(feature = "no-std", no_std)]
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::just_underscores_and_digits)]
 
 extern crate alloc;
 
+pub mod atlas;
+pub mod bitmap;
+pub mod bmfont;
+mod boolean;
+mod bvh;
+mod coloring;
+pub mod correction;
 #[cfg(feature = "ttf-parser")]
 mod font;
+pub mod frame;
+#[cfg(feature = "image")]
+mod image_buffer;
+pub mod json;
 mod math;
+pub mod metrics;
+mod overlap;
 pub mod raster;
+pub mod render;
 mod scanline;
 pub mod shape;
+pub mod svg;
+pub mod transform;
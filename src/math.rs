@@ -2,6 +2,7 @@ use core::ops::{Add, Mul};
 use num_traits::float::FloatConst;
 use num_traits::real::Real;
 use smallvec::SmallVec;
+use vek::Vec2;
 
 #[inline]
 pub fn min<T>(a: T, b: T) -> T
@@ -20,7 +21,7 @@ pub fn max<T>(a: T, b: T) -> T
 where
     T: PartialOrd<T>,
 {
-    if b < a {
+    if a < b {
         b
     } else {
         a
@@ -43,6 +44,22 @@ where
     a * (S::one() - weight) + b * weight
 }
 
+/// The z-component of the cross product of two 2D vectors.
+#[inline]
+pub fn cross<S: Real>(a: Vec2<S>, b: Vec2<S>) -> S {
+    a.x * b.y - a.y * b.x
+}
+
+/// Returns `1` for positive values and `-1` otherwise.
+#[inline]
+pub fn non_zero_sign<S: Real>(value: S) -> S {
+    if value > S::zero() {
+        S::one()
+    } else {
+        -S::one()
+    }
+}
+
 /// Solves the linear equation `b*x + a = 0`.
 pub fn solve_linear<S: Real>(b: S, a: S) -> Option<S> {
     if b.abs() > S::epsilon() {
@@ -70,7 +87,7 @@ pub fn solve_quadratic<S: Real>(c: S, b: S, a: S) -> SmallVec<[S; 2]> {
             let dscr = dscr.sqrt();
             solution.push((-b + dscr) / two_c);
             solution.push((-b - dscr) / two_c);
-        } else if !(dscr < -S::epsilon()) {
+        } else if dscr >= -S::epsilon() {
             solution.push(-b / two_c);
         }
     }
@@ -106,7 +123,8 @@ pub fn solve_cubic_depressed<S: Real + FloatConst>(b: S, a: S) -> SmallVec<[S; 3
             let x1 = (sq - a_half).cbrt() - (sq + a_half).cbrt();
             solution.push(x1);
             if d.abs() < S::epsilon() {
-                solution.push(a_half);
+                // double root
+                solution.push(-x1 / _2);
             }
         }
     }
@@ -116,7 +134,7 @@ pub fn solve_cubic_depressed<S: Real + FloatConst>(b: S, a: S) -> SmallVec<[S; 3
 /// Solves the normalized cubic equation `x^3 + c*x^2 + b*x + a = 0`.
 pub fn solve_cubic_normalized<S: Real + FloatConst>(c: S, b: S, a: S) -> SmallVec<[S; 3]> {
     if c.abs() < S::epsilon() {
-        solve_cubic_depressed(b, c)
+        solve_cubic_depressed(b, a)
     } else {
         let mut solution = SmallVec::new();
 
@@ -143,12 +161,12 @@ pub fn solve_cubic_normalized<S: Real + FloatConst>(c: S, b: S, a: S) -> SmallVe
             solution.push(sqrt_q_2 * (phi_3 - two_third_pi).cos() - c_thirds);
             solution.push(sqrt_q_2 * (phi_3 + two_third_pi).cos() - c_thirds);
         } else {
-            let d = d.sqrt();
+            let d = d.max(S::zero()).sqrt();
             let s = (r + d).cbrt();
             let t = (r - d).cbrt();
 
             solution.push(s + t - c_thirds);
-            if (s - t).abs() < S::epsilon() && (s + t) > S::epsilon() {
+            if (s - t).abs() < S::epsilon() && (s + t).abs() > S::epsilon() {
                 solution.push(-(s + t) / _2 - c_thirds);
             }
         }
@@ -165,6 +183,74 @@ pub fn solve_cubic<S: Real + FloatConst>(d: S, c: S, b: S, a: S) -> SmallVec<[S;
     } else if c.abs() < S::epsilon() {
         solve_cubic_depressed(b / d, a / d)
     } else {
-        solve_cubic_normalized(b / d, c / d, a / d)
+        solve_cubic_normalized(c / d, b / d, a / d)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks that `roots` are exactly the distinct `expected` roots of the
+    /// polynomial with the coefficients `[d, c, b, a]`.
+    fn assert_roots(roots: &[f64], coefficients: [f64; 4], expected: &[f64]) {
+        let [d, c, b, a] = coefficients;
+        for &x in roots {
+            let value = ((d * x + c) * x + b) * x + a;
+            assert!(value.abs() < 1e-9, "{} is no root of {:?}", x, coefficients);
+        }
+        for &x in expected {
+            assert!(
+                roots.iter().any(|root| (root - x).abs() < 1e-6),
+                "missing root {} of {:?} in {:?}",
+                x,
+                coefficients,
+                roots
+            );
+        }
+    }
+
+    #[test]
+    fn cubic_with_three_roots() {
+        let roots = solve_cubic(1.0, -6.0, 11.0, -6.0);
+        assert_roots(&roots, [1.0, -6.0, 11.0, -6.0], &[1.0, 2.0, 3.0]);
+        let roots = solve_cubic(2.0, -12.0, 22.0, -12.0);
+        assert_roots(&roots, [2.0, -12.0, 22.0, -12.0], &[1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn cubic_with_one_real_root() {
+        let roots = solve_cubic(1.0, -2.0, 1.0, -2.0);
+        assert_roots(&roots, [1.0, -2.0, 1.0, -2.0], &[2.0]);
+        assert_eq!(roots.len(), 1);
+    }
+
+    #[test]
+    fn cubic_with_double_root() {
+        let roots = solve_cubic(1.0, -5.0, 7.0, -3.0);
+        assert_roots(&roots, [1.0, -5.0, 7.0, -3.0], &[1.0, 3.0]);
+    }
+
+    #[test]
+    fn depressed_cubic() {
+        let roots = solve_cubic(1.0, 0.0, -7.0, 6.0);
+        assert_roots(&roots, [1.0, 0.0, -7.0, 6.0], &[-3.0, 1.0, 2.0]);
+        let roots = solve_cubic_normalized(0.0, -7.0, 6.0);
+        assert_roots(&roots, [1.0, 0.0, -7.0, 6.0], &[-3.0, 1.0, 2.0]);
+        let roots = solve_cubic(1.0, 0.0, 1.0, 2.0);
+        assert_roots(&roots, [1.0, 0.0, 1.0, 2.0], &[-1.0]);
+        assert_eq!(roots.len(), 1);
+    }
+
+    #[test]
+    fn depressed_cubic_with_double_root() {
+        let roots = solve_cubic_depressed(-12.0, 16.0);
+        assert_roots(&roots, [1.0, 0.0, -12.0, 16.0], &[-4.0, 2.0]);
+    }
+
+    #[test]
+    fn degenerate_cubic() {
+        let roots = solve_cubic(0.0, 1.0, -3.0, 2.0);
+        assert_roots(&roots, [0.0, 1.0, -3.0, 2.0], &[1.0, 2.0]);
     }
 }
@@ -0,0 +1,110 @@
+//! Font metrics and kerning of the glyphs of an atlas.
+
+#[cfg(feature = "ttf-parser")]
+use alloc::vec::Vec;
+use num_traits::real::Real;
+
+/// The line metrics of a font (in shape units).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FontMetrics<S> {
+    /// The size of the em (units per em for fonts).
+    pub em_size: S,
+    /// The distance between two baselines.
+    pub line_height: S,
+    pub ascender: S,
+    pub descender: S,
+    pub underline_y: S,
+    pub underline_thickness: S,
+}
+
+impl<S: Real> Default for FontMetrics<S> {
+    fn default() -> Self {
+        FontMetrics {
+            em_size: S::one(),
+            line_height: S::one(),
+            ascender: S::one(),
+            descender: S::zero(),
+            underline_y: S::zero(),
+            underline_thickness: S::zero(),
+        }
+    }
+}
+
+/// The kerning between two glyphs of an atlas.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct KerningPair<S> {
+    /// The index of the left glyph.
+    pub first: usize,
+    /// The index of the right glyph.
+    pub second: usize,
+    /// The adjustment of the advance (in shape units).
+    pub advance: S,
+}
+
+#[cfg(feature = "ttf-parser")]
+impl<S: Real> FontMetrics<S> {
+    /// Reads the metrics of `face`.
+    pub fn from_face(face: &ttf_parser::Face) -> Self {
+        let from = |value: i16| -> S { S::from(value).unwrap() };
+        let underline = face.underline_metrics();
+        FontMetrics {
+            em_size: S::from(face.units_per_em().unwrap_or(1024)).unwrap(),
+            line_height: from(face.height()) + from(face.line_gap()),
+            ascender: from(face.ascender()),
+            descender: from(face.descender()),
+            underline_y: underline.map_or(S::zero(), |line| from(line.position)),
+            underline_thickness: underline.map_or(S::zero(), |line| from(line.thickness)),
+        }
+    }
+}
+
+/// Collects the horizontal kerning of all pairs of `glyphs`, which were added
+/// from `face`.
+///
+/// The kerning of each pair of distinct glyph ids is only looked up once, even
+/// if several glyphs of the atlas share a glyph id.
+#[cfg(feature = "ttf-parser")]
+pub fn kerning_pairs<S: Real>(
+    face: &ttf_parser::Face,
+    glyphs: &[crate::atlas::AtlasGlyph<S>],
+) -> Vec<KerningPair<S>> {
+    use alloc::collections::BTreeMap;
+    use ttf_parser::GlyphId;
+
+    let subtables: Vec<_> = face
+        .kerning_subtables()
+        .filter(|table| table.is_horizontal() && !table.is_variable() && !table.has_cross_stream())
+        .collect();
+    let mut pairs = Vec::new();
+    if subtables.is_empty() {
+        return pairs;
+    }
+    // the indices of the glyphs with each glyph id
+    let mut indices: BTreeMap<u16, Vec<usize>> = BTreeMap::new();
+    for (index, glyph) in glyphs.iter().enumerate() {
+        if let Some(id) = glyph.glyph_id {
+            indices.entry(id).or_default().push(index);
+        }
+    }
+    for (&left, firsts) in &indices {
+        for (&right, seconds) in &indices {
+            let advance = subtables
+                .iter()
+                .filter_map(|table| table.glyphs_kerning(GlyphId(left), GlyphId(right)))
+                .fold(0, |sum, value| sum + value as i32);
+            if advance == 0 {
+                continue;
+            }
+            let advance = S::from(advance).unwrap();
+            for &first in firsts {
+                pairs.extend(seconds.iter().map(|&second| KerningPair {
+                    first,
+                    second,
+                    advance,
+                }));
+            }
+        }
+    }
+    pairs.sort_unstable_by_key(|pair| (pair.first, pair.second));
+    pairs
+}
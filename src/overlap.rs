@@ -0,0 +1,331 @@
+//! Distances of shapes with overlapping contours.
+//!
+//! The closest edge of the whole shape may lie inside of another contour
+//! (e.g. where two strokes of a glyph overlap), which gives a distance field
+//! with edges inside of the shape. Instead, the closest edge of every contour
+//! is searched, and the distances of the contours are combined by their
+//! winding, like msdfgen's `OverlappingContourCombiner`.
+
+use alloc::vec::Vec;
+use approx::RelativeEq;
+use num_traits::{real::Real, FloatConst};
+use smallvec::SmallVec;
+
+use crate::math::{max, median};
+use crate::shape::{
+    aabr_potentialli_contains_circle, replaces, Contour, EdgeCandidate, EdgeColor, Point2, Shape,
+};
+
+/// Selects the distance, which is combined.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum DistanceKind {
+    /// The true signed distance.
+    True,
+    /// The signed pseudo-distance.
+    Pseudo,
+    /// The signed pseudo-distance for each color channel.
+    MultiChannel,
+}
+
+/// The closest edges of a contour, or of several contours after merging.
+#[derive(Copy, Clone, Debug)]
+struct Selection<S> {
+    /// The closest edge of any color.
+    all: Option<EdgeCandidate<S>>,
+    /// The closest edge for each color channel.
+    channels: [Option<EdgeCandidate<S>>; 3],
+}
+
+impl<S: Real> Selection<S> {
+    fn new() -> Self {
+        Selection {
+            all: None,
+            channels: [None; 3],
+        }
+    }
+
+    /// The distance, within which an edge of `color` may improve the
+    /// selection.
+    fn bound(&self, color: EdgeColor) -> S {
+        let bound = |best: &Option<EdgeCandidate<S>>, bound: S| match best {
+            Some((distance, _, _)) => max(bound, distance.distance.abs()),
+            None => S::max_value(),
+        };
+        let mut result = bound(&self.all, S::zero());
+        for (channel, best) in self.channels.iter().enumerate() {
+            if color.has_channel(channel) {
+                result = bound(best, result);
+            }
+        }
+        result
+    }
+
+    fn add(&mut self, candidate: EdgeCandidate<S>, color: EdgeColor) {
+        let (distance, index, _) = candidate;
+        if replaces(&distance, index, &self.all) {
+            self.all = Some(candidate);
+        }
+        for (channel, best) in self.channels.iter_mut().enumerate() {
+            if color.has_channel(channel) && replaces(&distance, index, best) {
+                *best = Some(candidate);
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        let merge = |best: &mut Option<EdgeCandidate<S>>, other: &Option<EdgeCandidate<S>>| {
+            if let Some((distance, index, _)) = other {
+                if replaces(distance, *index, best) {
+                    *best = *other;
+                }
+            }
+        };
+        merge(&mut self.all, &other.all);
+        for (best, other) in self.channels.iter_mut().zip(other.channels.iter()) {
+            merge(best, other);
+        }
+    }
+}
+
+/// Combines the distances of the contours of a shape by their winding.
+pub(crate) struct ContourCombiner<'a, S> {
+    shape: &'a Shape<S>,
+    /// The index of the contour of each edge.
+    contours: Vec<usize>,
+    /// The winding of each contour.
+    windings: Vec<i32>,
+}
+
+impl<'a, S> ContourCombiner<'a, S>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    pub fn new(shape: &'a Shape<S>) -> Self {
+        let mut contours = alloc::vec![0; shape.edges.len()];
+        let windings = shape
+            .contour_ranges()
+            .into_iter()
+            .enumerate()
+            .map(|(contour, range)| {
+                for i in range.clone() {
+                    contours[i] = contour;
+                }
+                Contour::new(&shape.edges[range]).winding()
+            })
+            .collect();
+        ContourCombiner {
+            shape,
+            contours,
+            windings,
+        }
+    }
+
+    /// Finds the closest edges of each contour to `point`.
+    fn select(&self, point: Point2<S>, epsilon: S) -> SmallVec<[Selection<S>; 4]> {
+        let mut selections: SmallVec<[Selection<S>; 4]> =
+            SmallVec::from_elem(Selection::new(), self.windings.len());
+        let shape = self.shape;
+        shape
+            .bvh
+            .visit(&shape.edges, point, S::max_value(), |index, edge| {
+                let selection = &mut selections[self.contours[index]];
+                let bound = selection.bound(edge.color);
+                if bound > S::zero() && aabr_potentialli_contains_circle(edge.aabr, point, bound) {
+                    let (distance, param) = edge.signed_distance(point, epsilon);
+                    selection.add((distance, index, param), edge.color);
+                }
+                selections.iter().fold(S::zero(), |bound, selection| {
+                    max(bound, selection.bound(EdgeColor::WHITE))
+                })
+            });
+        selections
+    }
+
+    /// The distance of a selection for each channel, which is the same for
+    /// all channels unless `kind` is `DistanceKind::MultiChannel`.
+    fn distance(&self, selection: &Selection<S>, point: Point2<S>, kind: DistanceKind) -> [S; 3] {
+        let pseudo_distance = |best: &Option<EdgeCandidate<S>>| match *best {
+            Some((mut distance, index, param)) => {
+                self.shape.edges[index].distance_to_pseudo_distance(&mut distance, point, param);
+                distance.distance
+            }
+            None => -S::max_value(),
+        };
+        match kind {
+            DistanceKind::True => {
+                let distance = selection
+                    .all
+                    .map_or(-S::max_value(), |(distance, _, _)| distance.distance);
+                [distance; 3]
+            }
+            DistanceKind::Pseudo => [pseudo_distance(&selection.all); 3],
+            DistanceKind::MultiChannel => [
+                pseudo_distance(&selection.channels[0]),
+                pseudo_distance(&selection.channels[1]),
+                pseudo_distance(&selection.channels[2]),
+            ],
+        }
+    }
+
+    /// Computes the signed distance of `point` selected by `kind`, where
+    /// edges of a contour, which lie inside of another contour, are ignored.
+    ///
+    /// Positive distances are inside of contours with a winding of `1`.
+    pub fn combined_distance(&self, point: Point2<S>, epsilon: S, kind: DistanceKind) -> [S; 3] {
+        let resolve = |distance: &[S; 3]| median(distance[0], distance[1], distance[2]);
+        let selections = self.select(point, epsilon);
+        let distances: SmallVec<[[S; 3]; 4]> = selections
+            .iter()
+            .map(|selection| self.distance(selection, point, kind))
+            .collect();
+
+        let (mut all, mut inner, mut outer) =
+            (Selection::new(), Selection::new(), Selection::new());
+        for ((selection, distance), &winding) in
+            selections.iter().zip(&distances).zip(&self.windings)
+        {
+            all.merge(selection);
+            if winding > 0 && resolve(distance) >= S::zero() {
+                inner.merge(selection);
+            }
+            if winding < 0 && resolve(distance) <= S::zero() {
+                outer.merge(selection);
+            }
+        }
+        let shape_distance = self.distance(&all, point, kind);
+        let inner_distance = self.distance(&inner, point, kind);
+        let outer_distance = self.distance(&outer, point, kind);
+        let (inner_scalar, outer_scalar) = (resolve(&inner_distance), resolve(&outer_distance));
+
+        let (mut distance, winding) = if inner_scalar >= S::zero()
+            && inner_scalar.abs() <= outer_scalar.abs()
+        {
+            // inside of a contour: the distance to the farthest boundary
+            // of the contours, which contain the point
+            let mut distance = inner_distance;
+            for (contour, &winding) in distances.iter().zip(&self.windings) {
+                let scalar = resolve(contour);
+                if winding > 0 && scalar.abs() < outer_scalar.abs() && scalar > resolve(&distance) {
+                    distance = *contour;
+                }
+            }
+            (distance, 1)
+        } else if outer_scalar <= S::zero() && outer_scalar.abs() < inner_scalar.abs() {
+            // the same for the contours with a negative winding
+            let mut distance = outer_distance;
+            for (contour, &winding) in distances.iter().zip(&self.windings) {
+                let scalar = resolve(contour);
+                if winding < 0 && scalar.abs() < inner_scalar.abs() && scalar < resolve(&distance) {
+                    distance = *contour;
+                }
+            }
+            (distance, -1)
+        } else {
+            return shape_distance;
+        };
+        // contours of the opposite winding may cut into the selected one
+        for (contour, &contour_winding) in distances.iter().zip(&self.windings) {
+            let scalar = resolve(contour);
+            if contour_winding != winding
+                && scalar * resolve(&distance) >= S::zero()
+                && scalar.abs() < resolve(&distance).abs()
+            {
+                distance = *contour;
+            }
+        }
+        if resolve(&distance) == resolve(&shape_distance) {
+            distance = shape_distance;
+        }
+        distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::bitmap::{Bitmap, BitmapBuf};
+    use crate::raster::{DistanceMapping, FieldType, Rasterizer, YAxis};
+    use crate::shape::ColoringStrategy;
+    use crate::svg::parse_path;
+
+    const EPSILON: f64 = 1e-6;
+
+    /// Two clockwise squares, where the right edge of the first one and the
+    /// left edge of the second one lie inside of the other square.
+    fn overlapping_squares() -> Shape<f64> {
+        let mut shape = parse_path("M 0 0 L 0 4 L 4 4 L 4 0 Z M 2 1 L 2 3 L 6 3 L 6 1 Z").unwrap();
+        shape.color_edges(ColoringStrategy::Simple, 3.0, 0);
+        shape
+    }
+
+    /// The distances (in pixels) of the pixels of the row through the middle
+    /// of the overlap, resolved to one value per pixel.
+    fn middle_row(field_type: FieldType, overlap_support: bool) -> Vec<f32> {
+        let rasterizer = Rasterizer::new()
+            .with_scale(4.0)
+            .with_y_axis(YAxis::Down)
+            .with_overlap_support(overlap_support);
+        let shape = overlapping_squares();
+        let channels = field_type.channels();
+        let mut bitmap = BitmapBuf::<f32>::new(24, 16, channels);
+        let mapping = DistanceMapping::Pixels;
+        rasterizer.rasterize(field_type, &shape, 8.0, mapping, &mut bitmap);
+        bitmap
+            .row(8)
+            .chunks_exact(channels)
+            .map(|pixel| match *pixel {
+                [r, g, b] => median(r, g, b),
+                _ => pixel[0],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn no_edges_inside_of_overlapping_contours() {
+        for &field_type in &[FieldType::Sdf, FieldType::Psdf, FieldType::Msdf] {
+            // the pixels between x = 1 and x = 5 (shape units), which are
+            // at least 4 pixels away from the outline
+            let combined = middle_row(field_type, true);
+            for (x, &distance) in combined.iter().enumerate().take(20).skip(4) {
+                assert!(distance >= 3.5, "{:?} {} {}", field_type, x, distance);
+            }
+            // the edges inside of the other square show up without combining
+            let plain = middle_row(field_type, false);
+            assert!(plain[7].abs() <= 0.5, "{:?} {:?}", field_type, plain);
+            assert!(plain[15].abs() <= 0.5, "{:?} {:?}", field_type, plain);
+        }
+    }
+
+    #[test]
+    fn disjoint_contours_match_the_shape_distance() {
+        let mut shape = parse_path(
+            "M 0 0 L 0 4 L 4 4 L 4 0 Z \
+             M 6 0 Q 6 4 8 4 C 10 4 12 2 10 0 Z",
+        )
+        .unwrap();
+        shape.color_edges(ColoringStrategy::Simple, 3.0, 0);
+        let combiner = ContourCombiner::new(&shape);
+        assert_eq!(combiner.windings, [1, 1]);
+        for i in 0..=28 {
+            for j in 0..=12 {
+                let point = Point2::new(i as f64 * 0.5 - 1.0, j as f64 * 0.5 - 1.0);
+                let [distance, _, _] =
+                    combiner.combined_distance(point, EPSILON, DistanceKind::True);
+                let (expected, _) = shape.closest_point(point, f64::MAX, EPSILON).unwrap();
+                assert_eq!(distance.abs(), expected, "{:?}", point);
+                let [distance, _, _] =
+                    combiner.combined_distance(point, EPSILON, DistanceKind::Pseudo);
+                let expected = shape.closest_pseudo_distance(point, f64::MAX, EPSILON);
+                assert_eq!(Some(distance), expected, "{:?}", point);
+                assert_eq!(
+                    combiner.combined_distance(point, EPSILON, DistanceKind::MultiChannel),
+                    shape.multi_channel_distance(point, EPSILON),
+                    "{:?}",
+                    point
+                );
+            }
+        }
+    }
+}
@@ -3,13 +3,186 @@ use num_traits::{real::Real, FloatConst, NumCast};
 use vek::Vec2;
 
 use crate::{
-    math::{max, min},
-    shape::{Point2, Shape},
+    bitmap::{Bitmap, Channel},
+    math::{max, median, min},
+    overlap::{ContourCombiner, DistanceKind},
+    scanline::Scanline,
+    shape::{FillRule, Point2, Shape},
+    transform::Transform,
 };
 
+/// `Send + Sync` with the "parallel" feature, which shares the scalars and
+/// channels between the threads of the pool. Implemented for all types
+/// otherwise.
+#[cfg(feature = "parallel")]
+pub trait MaybeSync: Send + Sync {}
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync> MaybeSync for T {}
+
+/// `Send + Sync` with the "parallel" feature, which shares the scalars and
+/// channels between the threads of the pool. Implemented for all types
+/// otherwise.
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "parallel"))]
+impl<T> MaybeSync for T {}
+
+/// The direction of the y-axis of the pixel coordinates in a `Bitmap`,
+/// whose first row is at the top.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum YAxis {
+    /// The y-axis points upwards, so the first row is the one with the
+    /// largest y (like glyphs of fonts, shown upright in an image).
+    Up,
+    /// The y-axis points downwards, so the first row is the one with the
+    /// smallest y (like SVG paths, or textures for APIs with the origin at
+    /// the bottom-left).
+    Down,
+}
+
+impl Default for YAxis {
+    #[inline]
+    fn default() -> Self {
+        YAxis::Up
+    }
+}
+
 pub struct Rasterizer<S> {
-    pub scale: Vec2<S>,
-    pub translate: Vec2<S>,
+    /// The transformation from shape coordinates into pixel coordinates.
+    pub transform: Transform<S>,
+    pub y_axis: YAxis,
+    /// Decides, which pixels are inside of the shape, for bitmaps and the
+    /// sign of distances.
+    pub fill_rule: FillRule,
+    /// Combines the distances of the contours by their winding, so contours,
+    /// which overlap each other, don't produce edges inside of the shape.
+    pub overlap_support: bool,
+}
+
+impl<S> Default for Rasterizer<S>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Selects how the distances are written into the `Bitmap`.
+///
+/// Positive distances are inside the shape, negative ones outside.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DistanceMapping {
+    /// Signed distances in shape units.
+    Shape,
+    /// Signed distances in pixels.
+    Pixels,
+    /// Signed distances mapped from `-range..=range` pixels to `0..=1`, so
+    /// the edge is at `0.5`.
+    Normalized,
+}
+
+/// Selects the kind of image, a `Rasterizer` produces.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    /// The fill of the shape (see `Rasterizer::rasterize_bitmap`).
+    Bitmap,
+    /// The true signed distance (see `Rasterizer::rasterize_sdf`).
+    Sdf,
+    /// The signed pseudo-distance (see `Rasterizer::rasterize_psdf`).
+    Psdf,
+    /// The multi-channel signed distance (see `Rasterizer::rasterize_msdf`).
+    Msdf,
+    /// The multi-channel and true signed distance (see
+    /// `Rasterizer::rasterize_mtsdf`).
+    Mtsdf,
+}
+
+impl FieldType {
+    /// The number of channels of the produced image.
+    pub fn channels(self) -> usize {
+        match self {
+            FieldType::Bitmap | FieldType::Sdf | FieldType::Psdf => 1,
+            FieldType::Msdf => 3,
+            FieldType::Mtsdf => 4,
+        }
+    }
+
+    /// Returns `true` for multi-channel distance fields, which require
+    /// colored edges.
+    pub fn is_multi_channel(self) -> bool {
+        matches!(self, FieldType::Msdf | FieldType::Mtsdf)
+    }
+}
+
+/// Computes the unsigned true distance of `point`, clamped to `range`.
+///
+/// With a `combiner`, the distances of overlapping contours are combined.
+pub(crate) fn true_distance<S>(
+    shape: &Shape<S>,
+    combiner: Option<&ContourCombiner<'_, S>>,
+    point: Point2<S>,
+    range: S,
+    epsilon: S,
+) -> S
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    match combiner {
+        Some(combiner) => {
+            let [distance, _, _] = combiner.combined_distance(point, epsilon, DistanceKind::True);
+            min(distance.abs(), range)
+        }
+        None => shape
+            .closest_point(point, range, epsilon)
+            .map_or(range, |(distance, _)| distance),
+    }
+}
+
+/// Computes the unsigned pseudo-distance of `point`, clamped to `range`.
+fn pseudo_distance<S>(
+    shape: &Shape<S>,
+    combiner: Option<&ContourCombiner<'_, S>>,
+    point: Point2<S>,
+    range: S,
+    epsilon: S,
+) -> S
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    match combiner {
+        Some(combiner) => {
+            let [distance, _, _] = combiner.combined_distance(point, epsilon, DistanceKind::Pseudo);
+            min(distance.abs(), range)
+        }
+        None => shape
+            .closest_pseudo_distance(point, range, epsilon)
+            .map_or(range, |distance| distance.abs()),
+    }
+}
+
+/// Computes the multi-channel distance of `point`, where the sign of the
+/// median matches the fill of the scanline.
+fn filled_multi_channel_distance<S>(
+    shape: &Shape<S>,
+    combiner: Option<&ContourCombiner<'_, S>>,
+    point: Point2<S>,
+    filled: bool,
+    epsilon: S,
+) -> [S; 3]
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    let mut distances = match combiner {
+        Some(combiner) => combiner.combined_distance(point, epsilon, DistanceKind::MultiChannel),
+        None => shape.multi_channel_distance(point, epsilon),
+    };
+    if (median(distances[0], distances[1], distances[2]) > S::zero()) != filled {
+        for distance in &mut distances {
+            *distance = -*distance;
+        }
+    }
+    distances
 }
 
 impl<S> Rasterizer<S>
@@ -18,84 +191,410 @@ where
 {
     pub fn new() -> Self {
         Rasterizer {
-            scale: Vec2::new(S::one(), S::one()),
-            translate: Vec2::new(S::zero(), S::zero()),
+            transform: Transform::identity(),
+            y_axis: YAxis::Up,
+            fill_rule: FillRule::default(),
+            overlap_support: false,
         }
     }
 
-    pub fn with_scale(mut self, scale: S) -> Self {
-        self.scale = Vec2::new(scale, scale);
-        self
+    /// Scales the shape by `scale` (in pixels per shape unit).
+    ///
+    /// Together with `with_translate` this is the transformation
+    /// `(point + translate) * scale`, which replaces the linear part of the
+    /// current transformation.
+    pub fn with_scale(self, scale: S) -> Self {
+        self.with_scale2(scale, scale)
     }
     pub fn with_scale2(mut self, scale_x: S, scale_y: S) -> Self {
-        self.scale = Vec2::new(scale_x, scale_y);
-        self
+        let translate = self
+            .transform
+            .inverse()
+            .map_or_else(Vec2::zero, |inverse| -inverse.transform_point(Vec2::zero()));
+        self.transform = Transform::scaling(scale_x, scale_y);
+        self.with_translate(translate.x, translate.y)
     }
+    /// Translates the shape by `x` and `y` (in shape units) before scaling.
     pub fn with_translate(mut self, x: S, y: S) -> Self {
-        self.translate = Vec2::new(x, y);
+        let offset = self.transform.transform_vector(Vec2::new(x, y));
+        self.transform.e = offset.x;
+        self.transform.f = offset.y;
+        self
+    }
+    /// Replaces the transformation from shape coordinates into pixel
+    /// coordinates.
+    pub fn with_transform(mut self, transform: Transform<S>) -> Self {
+        self.transform = transform;
+        self
+    }
+    /// Applies `transform` to the shape before the current transformation,
+    /// e.g. a skew for synthetic italics.
+    pub fn with_pre_transform(mut self, transform: Transform<S>) -> Self {
+        self.transform = self.transform * transform;
+        self
+    }
+    pub fn with_y_axis(mut self, y_axis: YAxis) -> Self {
+        self.y_axis = y_axis;
+        self
+    }
+    pub fn with_fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_rule = fill_rule;
+        self
+    }
+    /// Enables the combination of the distances of overlapping contours
+    /// (see `overlap_support`), which is slower, because the closest edge of
+    /// every contour has to be found.
+    pub fn with_overlap_support(mut self, overlap_support: bool) -> Self {
+        self.overlap_support = overlap_support;
         self
     }
 
-    pub fn rasterize_bitmap<F>(
+    /// Transforms a point from pixel coordinates into shape coordinates.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the transformation is not invertible.
+    #[inline]
+    pub fn unproject(&self, point: Vec2<S>) -> Point2<S> {
+        self.transform
+            .inverse()
+            .expect("the transformation is not invertible")
+            .transform_point(point)
+    }
+
+    /// Transforms a point from shape coordinates into pixel coordinates.
+    #[inline]
+    pub fn project(&self, point: Point2<S>) -> Vec2<S> {
+        self.transform.transform_point(point)
+    }
+
+    /// Returns `shape` in pixel coordinates, where distances are measured.
+    pub(crate) fn project_shape(&self, shape: &Shape<S>) -> Shape<S> {
+        let mut projected = shape.clone();
+        projected.transform(&self.transform);
+        projected
+    }
+
+    /// Returns the index of the row of a `Bitmap` with `height` rows, which
+    /// contains the pixel coordinate `y` (or the other way around).
+    #[inline]
+    pub(crate) fn row(&self, y: usize, height: usize) -> usize {
+        match self.y_axis {
+            YAxis::Up => height - y - 1,
+            YAxis::Down => y,
+        }
+    }
+
+    /// Whether the pixel coordinate `x` on the `scanline` of the projected
+    /// shape is inside.
+    #[inline]
+    pub(crate) fn is_filled(&self, scanline: &Scanline<S>, x: S) -> bool {
+        let winding = scanline.winding(x);
+        // a mirroring transformation reverses the direction of the contours
+        if self.transform.determinant() < S::zero() {
+            self.fill_rule.is_inside(-winding)
+        } else {
+            self.fill_rule.is_inside(winding)
+        }
+    }
+
+    /// Returns the combiner for the projected `shape`, if the overlap support
+    /// is enabled.
+    pub(crate) fn combiner<'a>(&self, shape: &'a Shape<S>) -> Option<ContourCombiner<'a, S>> {
+        if self.overlap_support {
+            Some(ContourCombiner::new(shape))
+        } else {
+            None
+        }
+    }
+
+    /// The precision of the closest point search in pixels.
+    #[inline]
+    pub(crate) fn epsilon(&self) -> S {
+        S::one() / <S as From<u16>>::from(256)
+    }
+
+    /// The number of pixels per shape unit, in the direction, in which the
+    /// transformation shrinks the most.
+    pub(crate) fn distance_scale(&self) -> S {
+        // the smallest singular value of the linear part
+        let t = &self.transform;
+        let _2 = S::one() + S::one();
+        let q = t.a * t.a + t.b * t.b + t.c * t.c + t.d * t.d;
+        let det = t.determinant();
+        let root = max(S::zero(), q * q - _2 * _2 * det * det).sqrt();
+        max(S::zero(), (q - root) / _2).sqrt()
+    }
+
+    /// Converts a signed distance in pixels as selected by `mapping`,
+    /// clamping it to `-range..=range` pixels.
+    pub(crate) fn map_distance(&self, distance: S, range: S, mapping: DistanceMapping) -> S {
+        let distance = max(-range, min(distance, range));
+        match mapping {
+            DistanceMapping::Shape => distance / self.distance_scale(),
+            DistanceMapping::Pixels => distance,
+            DistanceMapping::Normalized => {
+                let half = S::one() / (S::one() + S::one());
+                distance / (range + range) + half
+            }
+        }
+    }
+
+    /// Converts a value, which was produced by `map_distance`, back into a
+    /// signed distance in pixels.
+    pub(crate) fn unmap_distance(&self, value: S, range: S, mapping: DistanceMapping) -> S {
+        match mapping {
+            DistanceMapping::Shape => value * self.distance_scale(),
+            DistanceMapping::Pixels => value,
+            DistanceMapping::Normalized => {
+                let half = S::one() / (S::one() + S::one());
+                (value - half) * (range + range)
+            }
+        }
+    }
+
+    /// Calls `f` with the position and the fill of every pixel of `target`
+    /// and its channels. The position is in the pixel coordinates of the
+    /// `projected` shape.
+    ///
+    /// With the "parallel" feature, the rows are rendered concurrently, if
+    /// `target` exposes its storage by `Bitmap::rows_mut`.
+    fn rasterize_with<B, F>(&self, projected: &Shape<S>, target: &mut B, channels: usize, f: F)
+    where
+        S: MaybeSync,
+        B: Bitmap,
+        F: Fn(Point2<S>, bool, &mut [B::Channel]) + MaybeSync,
+    {
+        assert_eq!(target.channels(), channels, "unexpected number of channels");
+        let (width, height) = (target.width(), target.height());
+        let half = S::one() / (S::one() + S::one());
+        let render_row = |y: usize, row: &mut [B::Channel]| {
+            let p_y = <S as NumCast>::from(y).unwrap() + half;
+            let scanline = projected.scanline(p_y);
+            for (x, pixel) in row.chunks_exact_mut(channels).enumerate() {
+                let p_x = <S as NumCast>::from(x).unwrap() + half;
+                let filled = self.is_filled(&scanline, p_x);
+                f(Point2::new(p_x, p_y), filled, pixel);
+            }
+        };
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            match target.rows_mut() {
+                Some((data, stride)) if stride > 0 => {
+                    // `row` is its own inverse, so it maps the bitmap rows
+                    // back to the rows of the shape
+                    data.par_chunks_mut(stride)
+                        .take(height)
+                        .enumerate()
+                        .for_each(|(y, row)| {
+                            render_row(self.row(y, height), &mut row[..width * channels])
+                        });
+                    return;
+                }
+                _ => {}
+            }
+        }
+        for y in 0..height {
+            render_row(
+                y,
+                &mut target.row_mut(self.row(y, height))[..width * channels],
+            );
+        }
+    }
+
+    /// Rasterizes the fill of `shape` into a single channel `target`, where
+    /// filled pixels are `1` and empty pixels are `0`.
+    pub fn rasterize_bitmap<B: Bitmap>(&self, shape: &Shape<S>, target: &mut B)
+    where
+        S: MaybeSync,
+    {
+        let shape = &self.project_shape(shape);
+        self.rasterize_with(shape, target, 1, |_, filled, pixel| {
+            let value = if filled { S::one() } else { S::zero() };
+            pixel[0] = Channel::from_value(value);
+        });
+    }
+
+    /// Rasterizes the true signed distance of `shape` into a single channel
+    /// `target`.
+    ///
+    /// Distances are clamped to `-range..=range` pixels and converted as
+    /// selected by `mapping`.
+    pub fn rasterize_sdf<B: Bitmap>(
         &self,
         shape: &Shape<S>,
-        width: usize,
-        height: usize,
-        mut draw_pixel: F,
+        range: S,
+        mapping: DistanceMapping,
+        target: &mut B,
     ) where
-        F: FnMut(usize, usize, bool),
+        S: MaybeSync,
     {
-        let half = S::one() / (S::one() + S::one());
-        for y in 0..height {
-            let f_y: S = NumCast::from(y).unwrap();
-            let p_y = (f_y + half) / self.scale.y - self.translate.y;
-            let scanline = shape.scanline(p_y);
-            for x in 0..width {
-                let f_x: S = NumCast::from(x).unwrap();
-                let p_x = (f_x + half) / self.scale.x - self.translate.x;
-                let filled = scanline.is_filled(p_x);
-                draw_pixel(x, height - y - 1, filled);
+        let shape = &self.project_shape(shape);
+        let combiner = self.combiner(shape);
+        let epsilon = self.epsilon();
+        self.rasterize_with(shape, target, 1, |point, filled, pixel| {
+            let distance = true_distance(shape, combiner.as_ref(), point, range, epsilon);
+            let distance = if filled { distance } else { -distance };
+            pixel[0] = Channel::from_value(self.map_distance(distance, range, mapping));
+        });
+    }
+
+    /// Rasterizes the signed pseudo-distance of `shape` into a single channel
+    /// `target`.
+    ///
+    /// See `rasterize_sdf` for the meaning of `range` and `mapping`.
+    pub fn rasterize_psdf<B: Bitmap>(
+        &self,
+        shape: &Shape<S>,
+        range: S,
+        mapping: DistanceMapping,
+        target: &mut B,
+    ) where
+        S: MaybeSync,
+    {
+        let shape = &self.project_shape(shape);
+        let combiner = self.combiner(shape);
+        let epsilon = self.epsilon();
+        self.rasterize_with(shape, target, 1, |point, filled, pixel| {
+            let distance = pseudo_distance(shape, combiner.as_ref(), point, range, epsilon);
+            let distance = if filled { distance } else { -distance };
+            pixel[0] = Channel::from_value(self.map_distance(distance, range, mapping));
+        });
+    }
+
+    /// Rasterizes the multi-channel signed distance of `shape` into a three
+    /// channel `target`.
+    ///
+    /// See `rasterize_sdf` for the meaning of `range` and `mapping`.
+    pub fn rasterize_msdf<B: Bitmap>(
+        &self,
+        shape: &Shape<S>,
+        range: S,
+        mapping: DistanceMapping,
+        target: &mut B,
+    ) where
+        S: MaybeSync,
+    {
+        let shape = &self.project_shape(shape);
+        let combiner = self.combiner(shape);
+        let epsilon = self.epsilon();
+        self.rasterize_with(shape, target, 3, |point, filled, pixel| {
+            let distances =
+                filled_multi_channel_distance(shape, combiner.as_ref(), point, filled, epsilon);
+            for (value, distance) in pixel.iter_mut().zip(distances.iter()) {
+                *value = Channel::from_value(self.map_distance(*distance, range, mapping));
             }
-        }
+        });
     }
 
-    pub fn rasterize_sdf<F>(
+    /// Rasterizes the multi-channel signed distance of `shape` with the true
+    /// signed distance in the fourth channel into a four channel `target`.
+    ///
+    /// See `rasterize_sdf` for the meaning of `range` and `mapping`.
+    pub fn rasterize_mtsdf<B: Bitmap>(
         &self,
         shape: &Shape<S>,
-        width: usize,
-        height: usize,
-        offset: u8,
-        mut draw_pixel: F,
+        range: S,
+        mapping: DistanceMapping,
+        target: &mut B,
     ) where
-        F: FnMut(usize, usize, u8),
+        S: MaybeSync,
     {
-        let half = S::one() / (S::one() + S::one());
-        let min_scale = min(self.scale.x, self.scale.y);
-        let epsilon = min_scale / <S as From<u16>>::from(offset as u16);
-        let scaled_offset: S = <S as From<u16>>::from(offset as u16) / min_scale;
-        for y in 0..height {
-            let f_y: S = NumCast::from(y).unwrap();
-            let p_y = (f_y + half) / self.scale.y - self.translate.y;
-            let scanline = shape.scanline(p_y);
-            for x in 0..width {
-                let f_x: S = NumCast::from(x).unwrap();
-                let p_x = (f_x + half) / self.scale.x - self.translate.x;
-                let filled = scanline.is_filled(p_x);
-                let value = if let Some((distance, _)) =
-                    shape.closest_point(Point2::new(p_x, p_y), scaled_offset, epsilon)
-                {
-                    let distance = (distance * From::from(128) / scaled_offset)
-                        .to_u8()
-                        .unwrap();
-                    if filled {
-                        128 + distance
-                    } else {
-                        128 - distance
-                    }
-                } else {
-                    0
-                };
-                draw_pixel(x, height - y - 1, value);
+        let shape = &self.project_shape(shape);
+        let combiner = self.combiner(shape);
+        let epsilon = self.epsilon();
+        self.rasterize_with(shape, target, 4, |point, filled, pixel| {
+            let combiner = combiner.as_ref();
+            let [r, g, b] = filled_multi_channel_distance(shape, combiner, point, filled, epsilon);
+            let distance = true_distance(shape, combiner, point, range, epsilon);
+            let a = if filled { distance } else { -distance };
+            for (value, distance) in pixel.iter_mut().zip([r, g, b, a].iter()) {
+                *value = Channel::from_value(self.map_distance(*distance, range, mapping));
+            }
+        });
+    }
+
+    /// Rasterizes `shape` into `target` as selected by `field_type`.
+    ///
+    /// See `rasterize_sdf` for the meaning of `range` and `mapping` (both are
+    /// ignored for `FieldType::Bitmap`).
+    pub fn rasterize<B: Bitmap>(
+        &self,
+        field_type: FieldType,
+        shape: &Shape<S>,
+        range: S,
+        mapping: DistanceMapping,
+        target: &mut B,
+    ) where
+        S: MaybeSync,
+    {
+        match field_type {
+            FieldType::Bitmap => self.rasterize_bitmap(shape, target),
+            FieldType::Sdf => self.rasterize_sdf(shape, range, mapping, target),
+            FieldType::Psdf => self.rasterize_psdf(shape, range, mapping, target),
+            FieldType::Msdf => self.rasterize_msdf(shape, range, mapping, target),
+            FieldType::Mtsdf => self.rasterize_mtsdf(shape, range, mapping, target),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap::{BitmapBuf, BitmapView, RowByRow};
+    use crate::shape::ColoringStrategy;
+    use crate::svg::parse_path;
+
+    /// A glyph like shape with a hole, made of lines, quadratic and cubic
+    /// curves.
+    fn glyph() -> Shape<f64> {
+        let mut shape = parse_path(
+            "M 2 1 L 14 1 Q 15 8 12 11 C 10 15 4 15 3 12 Z \
+             M 5 4 C 5 9 10 10 11 5 Q 8 3 5 4 Z",
+        )
+        .unwrap();
+        shape.color_edges(ColoringStrategy::Simple, 3.0, 0);
+        shape
+    }
+
+    #[test]
+    fn concurrent_rows_match_the_row_loop() {
+        let shape = glyph();
+        for &y_axis in &[YAxis::Up, YAxis::Down] {
+            let rasterizer = Rasterizer::new()
+                .with_scale(2.0)
+                .with_translate(1.0, 2.0)
+                .with_y_axis(y_axis);
+            for &field_type in &[
+                FieldType::Bitmap,
+                FieldType::Sdf,
+                FieldType::Psdf,
+                FieldType::Msdf,
+                FieldType::Mtsdf,
+            ] {
+                let channels = field_type.channels();
+                let mut expected = RowByRow(BitmapBuf::<f32>::new(35, 33, channels));
+                let mapping = DistanceMapping::Pixels;
+                rasterizer.rasterize(field_type, &shape, 4.0, mapping, &mut expected);
+                let mut bitmap = BitmapBuf::new(35, 33, channels);
+                rasterizer.rasterize(field_type, &shape, 4.0, mapping, &mut bitmap);
+                assert_eq!(bitmap, expected.0, "{:?} {:?}", field_type, y_axis);
+
+                // a section of a larger bitmap, which doesn't start at a row
+                let mut larger = BitmapBuf::<f32>::new(40, 40, channels);
+                let mut view = BitmapView::new(&mut larger, 3, 5, 35, 33);
+                rasterizer.rasterize(field_type, &shape, 4.0, mapping, &mut view);
+                for y in 0..33 {
+                    assert_eq!(
+                        view.row(y),
+                        expected.row(y),
+                        "{:?} {:?}",
+                        field_type,
+                        y_axis
+                    );
+                }
+                assert!(larger.row(4).iter().all(|&value| value == 0.0));
             }
         }
     }
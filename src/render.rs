@@ -0,0 +1,171 @@
+//! Interpretation of distance fields, by rendering them back into
+//! anti-aliased bitmaps.
+
+use alloc::vec::Vec;
+use approx::RelativeEq;
+use num_traits::{real::Real, FloatConst, NumCast};
+
+use crate::{
+    bitmap::{Bitmap, Channel},
+    math::{max, median, min, mix},
+    raster::{DistanceMapping, Rasterizer},
+};
+
+/// Samples the values of all channels of `field` at the position `(x, y)`
+/// (in pixels) with bilinear interpolation.
+fn interpolate<S, B>(field: &B, x: S, y: S, values: &mut [S])
+where
+    S: Real + From<u16>,
+    B: Bitmap,
+{
+    let (width, height, channels) = (field.width(), field.height(), field.channels());
+    let half = S::one() / (S::one() + S::one());
+    let clamp = |value: S, size: usize| -> S {
+        let upper: S = NumCast::from(size - 1).unwrap();
+        max(S::zero(), min(value - half, upper))
+    };
+    let (x, y) = (clamp(x, width), clamp(y, height));
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+    let (x0, y0) = (x0.to_usize().unwrap(), y0.to_usize().unwrap());
+    let (x1, y1) = (min(x0 + 1, width - 1), min(y0 + 1, height - 1));
+    let (top, bottom) = (field.row(y0), field.row(y1));
+    for (c, value) in values.iter_mut().enumerate() {
+        let sample = |row: &[B::Channel], x: usize| -> S { row[x * channels + c].to_value() };
+        let top = mix(sample(top, x0), sample(top, x1), tx);
+        let bottom = mix(sample(bottom, x0), sample(bottom, x1), tx);
+        *value = mix(top, bottom, ty);
+    }
+}
+
+impl<S> Rasterizer<S>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    /// Renders a distance field, which was produced by this rasterizer with
+    /// the given `range` and `mapping`, into the single channel `output`.
+    ///
+    /// The field is scaled to the size of `output` with bilinear sampling.
+    /// Each output pixel gets the coverage of the shape (`0` outside, `1`
+    /// inside), with a linear transition of one pixel at the edge. If the
+    /// field is stretched, the transition is one pixel along the axis with
+    /// the smaller scale.
+    ///
+    /// Fields with three channels are interpreted as MSDF (using the median),
+    /// fields with four channels as MTSDF (using the median of the first three).
+    pub fn render_distance_field<F, O>(
+        &self,
+        field: &F,
+        range: S,
+        mapping: DistanceMapping,
+        output: &mut O,
+    ) where
+        F: Bitmap,
+        O: Bitmap,
+    {
+        assert_eq!(output.channels(), 1, "unexpected number of channels");
+        let (width, height) = (output.width(), output.height());
+        if field.width() == 0 || field.height() == 0 {
+            return;
+        }
+        let to_s = |value: usize| -> S { NumCast::from(value).unwrap() };
+        let half = S::one() / (S::one() + S::one());
+        let scale_x = to_s(field.width()) / to_s(width);
+        let scale_y = to_s(field.height()) / to_s(height);
+        // distances in field pixels have to be converted into output pixels,
+        // the smaller scale keeps the edge smooth along both axes
+        let output_scale = min(
+            to_s(width) / to_s(field.width()),
+            to_s(height) / to_s(field.height()),
+        );
+        let mut values: Vec<S> = alloc::vec![S::zero(); field.channels()];
+        for y in 0..height {
+            let row = output.row_mut(y);
+            for (x, pixel) in row[..width].iter_mut().enumerate() {
+                interpolate(
+                    field,
+                    (to_s(x) + half) * scale_x,
+                    (to_s(y) + half) * scale_y,
+                    &mut values,
+                );
+                let value = if values.len() >= 3 {
+                    median(values[0], values[1], values[2])
+                } else {
+                    values[0]
+                };
+                let distance = self.unmap_distance(value, range, mapping) * output_scale;
+                *pixel = Channel::from_value(max(S::zero(), min(distance + half, S::one())));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap::BitmapBuf;
+    use crate::raster::FieldType;
+    use crate::shape::ColoringStrategy;
+    use crate::svg::parse_path;
+
+    const EPSILON: f32 = 1e-4;
+
+    /// Renders the `field_type` field of a square with its edges through the
+    /// centers of the pixels `4` and `11` of a 16x16 field into an output of
+    /// `width` by `height` pixels.
+    fn render(field_type: FieldType, width: usize, height: usize) -> BitmapBuf<f32> {
+        let mut shape = parse_path("M 4.5 4.5 L 4.5 11.5 L 11.5 11.5 L 11.5 4.5 Z").unwrap();
+        shape.color_edges(ColoringStrategy::Simple, 3.0, 0);
+        let rasterizer = Rasterizer::new();
+        let mapping = DistanceMapping::Normalized;
+        let mut field = BitmapBuf::<f32>::new(16, 16, field_type.channels());
+        rasterizer.rasterize(field_type, &shape, 4.0, mapping, &mut field);
+        let mut output = BitmapBuf::new(width, height, 1);
+        rasterizer.render_distance_field(&field, 4.0, mapping, &mut output);
+        output
+    }
+
+    #[test]
+    fn square() {
+        for &field_type in &[FieldType::Sdf, FieldType::Msdf] {
+            let output = render(field_type, 16, 16);
+            let coverage = |x, y| output.pixel(x, y)[0];
+            // inside, outside and on the edges
+            assert_eq!(coverage(8, 8), 1.0, "{:?}", field_type);
+            assert_eq!(coverage(1, 8), 0.0, "{:?}", field_type);
+            assert_eq!(coverage(8, 14), 0.0, "{:?}", field_type);
+            for &(x, y) in &[(4, 8), (11, 8), (8, 4), (8, 11)] {
+                assert!((coverage(x, y) - 0.5).abs() < EPSILON, "{:?}", field_type);
+            }
+            // the transition is one pixel wide
+            for &(x, y) in &[(5, 8), (10, 8), (8, 5), (8, 10)] {
+                assert_eq!(coverage(x, y), 1.0, "{:?}", field_type);
+            }
+            for &(x, y) in &[(3, 8), (12, 8), (8, 3), (8, 12)] {
+                assert_eq!(coverage(x, y), 0.0, "{:?}", field_type);
+            }
+        }
+    }
+
+    #[test]
+    fn stretched_square() {
+        for &field_type in &[FieldType::Sdf, FieldType::Msdf] {
+            let output = render(field_type, 32, 16);
+            let coverage = |x, y| output.pixel(x, y)[0];
+            // the vertical edges are at 9 and 23, so the centers of the
+            // neighbouring pixels are a quarter pixel of the field away
+            for &x in &[8, 23] {
+                assert!((coverage(x, 8) - 0.25).abs() < EPSILON, "{:?}", field_type);
+            }
+            for &x in &[9, 22] {
+                assert!((coverage(x, 8) - 0.75).abs() < EPSILON, "{:?}", field_type);
+            }
+            // the horizontal edges aren't scaled
+            for &y in &[4, 11] {
+                assert!((coverage(16, y) - 0.5).abs() < EPSILON, "{:?}", field_type);
+            }
+            assert_eq!(coverage(16, 5), 1.0, "{:?}", field_type);
+            assert_eq!(coverage(16, 3), 0.0, "{:?}", field_type);
+        }
+    }
+}
@@ -3,10 +3,11 @@ use core::{cell::Cell, cmp::Ordering};
 use num_traits::{real::Real, FloatConst};
 use smallvec::SmallVec;
 
-use crate::shape::Shape;
+use crate::shape::{FillRule, Shape};
 
 pub struct Scanline<S> {
-    intersections: SmallVec<[S; 4]>,
+    /// The sorted crossings with the winding number right of each of them.
+    intersections: SmallVec<[(S, i32); 4]>,
     index: Cell<usize>,
 }
 
@@ -22,19 +23,28 @@ where
 
     fn move_to(&self, x: S) -> usize {
         let mut index = self.index.get();
-        while index > 0 && (index >= self.intersections.len() || x < self.intersections[index]) {
+        while index > 0 && (index >= self.intersections.len() || x < self.intersections[index].0) {
             index -= 1;
         }
-        while index < self.intersections.len() && x >= self.intersections[index] {
+        while index < self.intersections.len() && x >= self.intersections[index].0 {
             index += 1;
         }
         self.index.set(index);
         index
     }
 
+    /// Returns the winding number of the point `x` on the scanline.
     #[inline]
-    pub fn is_filled(&self, x: S) -> bool {
-        self.move_to(x) & 1 != 0
+    pub fn winding(&self, x: S) -> i32 {
+        match self.move_to(x) {
+            0 => 0,
+            index => self.intersections[index - 1].1,
+        }
+    }
+
+    #[inline]
+    pub fn is_filled(&self, x: S, fill_rule: FillRule) -> bool {
+        fill_rule.is_inside(self.winding(x))
     }
 }
 
@@ -43,8 +53,16 @@ where
     S: Real + FloatConst + RelativeEq + From<u16>,
 {
     pub fn scanline(&self, y: S) -> Scanline<S> {
-        let mut intersections = self.scanline_intersections(y);
-        intersections.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let mut crossings = self.scanline_intersections(y);
+        crossings.sort_unstable_by(|a, b| a.x.partial_cmp(&b.x).unwrap_or(Ordering::Equal));
+        let mut winding = 0;
+        let intersections = crossings
+            .iter()
+            .map(|crossing| {
+                winding += crossing.direction;
+                (crossing.x, winding)
+            })
+            .collect();
 
         Scanline {
             intersections,
@@ -52,3 +70,93 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::shape::{FillRule, Shape};
+    use crate::svg::parse_path;
+
+    fn windings(shape: &Shape<f64>, y: f64, xs: &[f64]) -> Vec<i32> {
+        let scanline = shape.scanline(y);
+        xs.iter().map(|&x| scanline.winding(x)).collect()
+    }
+
+    #[test]
+    fn shared_vertices() {
+        // a clockwise diamond, the scanlines run through its corners
+        let diamond = parse_path("M 0 -1 L -1 0 L 0 1 L 1 0 Z").unwrap();
+        let xs = [-1.5, -0.5, 0.0, 0.5, 1.5];
+        assert_eq!(windings(&diamond, 0.0, &xs), [0, 1, 1, 1, 0]);
+        assert_eq!(
+            windings(&diamond, 0.5, &[-0.75, -0.25, 0.25, 0.75]),
+            [0, 1, 1, 0]
+        );
+        // the top and bottom corners only touch the scanline
+        assert_eq!(windings(&diamond, 1.0, &xs), [0, 0, 0, 0, 0]);
+        assert_eq!(windings(&diamond, -1.0, &xs), [0, 0, 0, 0, 0]);
+
+        // a vertex, where the contour continues upwards
+        let zigzag = parse_path("M 0 0 L 1 2 L 0 4 L 4 4 L 4 0 Z").unwrap();
+        assert_eq!(windings(&zigzag, 2.0, &[0.5, 1.5, 4.5]), [0, 1, 0]);
+    }
+
+    #[test]
+    fn tangent_quadratic() {
+        // the curve has its top at (1, 1)
+        let arch = parse_path("M 0 0 Q 1 2 2 0 Z").unwrap();
+        let xs = [-0.5, 0.5, 1.0, 1.5, 2.5];
+        assert_eq!(windings(&arch, 1.0, &xs), [0, 0, 0, 0, 0]);
+        assert_eq!(windings(&arch, 0.99, &xs), [0, 0, 1, 0, 0]);
+        assert_eq!(windings(&arch, 1.01, &xs), [0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn nested_contours() {
+        let outer = "M 0 0 L 0 6 L 6 6 L 6 0 Z";
+        let xs = [-1.0, 1.0, 3.0, 5.0, 7.0];
+        let rules = [FillRule::NonZero, FillRule::EvenOdd, FillRule::Positive];
+        let cases = [
+            // the inner square in the same direction
+            (
+                "M 2 2 L 2 4 L 4 4 L 4 2 Z",
+                [0, 1, 2, 1, 0],
+                [true, true, true],
+            ),
+            // the inner square in the opposite direction
+            (
+                "M 2 2 L 4 2 L 4 4 L 2 4 Z",
+                [0, 1, 0, 1, 0],
+                [false, false, false],
+            ),
+        ];
+        for &(inner, expected, filled) in &cases {
+            let shape = parse_path(&alloc::format!("{} {}", outer, inner)).unwrap();
+            assert_eq!(windings(&shape, 3.0, &xs), expected, "{}", inner);
+            let scanline = shape.scanline(3.0);
+            for (&rule, &filled) in rules.iter().zip(&filled) {
+                let expected = filled && rule != FillRule::EvenOdd;
+                assert_eq!(
+                    scanline.is_filled(3.0, rule),
+                    expected,
+                    "{} {:?}",
+                    inner,
+                    rule
+                );
+                assert!(scanline.is_filled(1.0, rule), "{} {:?}", inner, rule);
+                assert!(!scanline.is_filled(7.0, rule), "{} {:?}", inner, rule);
+            }
+        }
+
+        // both squares counterclockwise
+        let shape = parse_path("M 0 0 L 6 0 L 6 6 L 0 6 Z M 2 2 L 4 2 L 4 4 L 2 4 Z").unwrap();
+        assert_eq!(windings(&shape, 3.0, &xs), [0, -1, -2, -1, 0]);
+        let scanline = shape.scanline(3.0);
+        assert!(scanline.is_filled(3.0, FillRule::NonZero));
+        assert!(!scanline.is_filled(3.0, FillRule::EvenOdd));
+        assert!(scanline.is_filled(1.0, FillRule::EvenOdd));
+        assert!(!scanline.is_filled(1.0, FillRule::Positive));
+        assert!(!scanline.is_filled(3.0, FillRule::Positive));
+    }
+}
@@ -1,20 +1,51 @@
 use alloc::vec::Vec;
 use approx::RelativeEq;
+use core::cmp::Ordering;
 use num_traits::{real::Real, FloatConst};
 use smallvec::SmallVec;
 
 pub use vek::{Aabr, CubicBezier2, LineSegment2, QuadraticBezier2, Vec2};
 
-use crate::math::{mix, solve_cubic, solve_linear, solve_quadratic};
+pub use crate::boolean::BooleanOp;
+use crate::bvh::Bvh;
+pub use crate::coloring::ColoringStrategy;
+use crate::math::{
+    cross, max, min, mix, non_zero_sign, solve_cubic, solve_linear, solve_quadratic,
+};
+use crate::transform::Transform;
 
 pub type Point2<S> = Vec2<S>;
 
+/// The closest edge found so far, with its signed distance, index and
+/// parameter.
+pub(crate) type EdgeCandidate<S> = (SignedDistance<S>, usize, S);
+
+/// Whether the edge at `index` with `distance` replaces `best`.
+///
+/// Ties are won by the first edge, so the result doesn't depend on the order
+/// in which the edges are searched.
+pub(crate) fn replaces<S: Real>(
+    distance: &SignedDistance<S>,
+    index: usize,
+    best: &Option<EdgeCandidate<S>>,
+) -> bool {
+    match best {
+        Some((best_distance, best_index, _)) => {
+            distance.is_closer_than(best_distance)
+                || (!best_distance.is_closer_than(distance) && index < *best_index)
+        }
+        None => true,
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Shape<S> {
-    edges: Vec<Edge<S>>,
+    pub(crate) edges: Vec<Edge<S>>,
     aabr: Aabr<S>,
+    pub(crate) bvh: Bvh<S>,
 }
 
-fn aabr_potentialli_contains_circle<S: Real>(
+pub(crate) fn aabr_potentialli_contains_circle<S: Real>(
     aabr: Aabr<S>,
     point: Point2<S>,
     max_distance: S,
@@ -34,12 +65,124 @@ where
         ShapeBuilder::new()
     }
 
+    /// Creates a shape from `edges`, or `None` if there are no edges.
+    pub(crate) fn from_edges(edges: Vec<Edge<S>>) -> Option<Self> {
+        let aabr = edges.first()?.aabr;
+        let mut shape = Shape {
+            edges,
+            aabr,
+            bvh: Bvh::new(&[]),
+        };
+        shape.update_bounds();
+        Some(shape)
+    }
+
+    /// Recomputes the bounding box and the edge hierarchy, after the edges
+    /// have been changed.
+    pub(crate) fn update_bounds(&mut self) {
+        let mut aabr = self.edges[0].aabr;
+        for edge in &self.edges[1..] {
+            aabr.expand_to_contain(edge.aabr);
+        }
+        self.aabr = aabr;
+        self.bvh = Bvh::new(&self.edges);
+    }
+
     #[inline]
     pub fn aabr(&self) -> Aabr<S> {
         self.aabr
     }
 
-    pub fn scanline_intersections(&self, y: S) -> SmallVec<[S; 4]> {
+    #[inline]
+    pub fn edges(&self) -> &[Edge<S>] {
+        &self.edges
+    }
+
+    /// Applies `transform` to all edges of the shape.
+    ///
+    /// Bézier curves are invariant under affine transformations, so this is
+    /// exact.
+    pub fn transform(&mut self, transform: &Transform<S>) {
+        for edge in &mut self.edges {
+            *edge = Edge::new(
+                edge.segment.transformed(transform),
+                edge.color,
+                edge.is_new_contour,
+            );
+        }
+        self.update_bounds();
+    }
+
+    /// Returns the index ranges of the edges of each contour.
+    pub(crate) fn contour_ranges(&self) -> Vec<core::ops::Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        for (i, edge) in self.edges.iter().enumerate().skip(1) {
+            if edge.is_new_contour {
+                ranges.push(start..i);
+                start = i;
+            }
+        }
+        if start < self.edges.len() {
+            ranges.push(start..self.edges.len());
+        }
+        ranges
+    }
+
+    /// Returns the contours of the shape.
+    pub fn contours(&self) -> Vec<Contour<'_, S>> {
+        self.contour_ranges()
+            .into_iter()
+            .map(|range| Contour::new(&self.edges[range]))
+            .collect()
+    }
+
+    /// Orients the contours consistently: outer contours clockwise (with the
+    /// y-axis pointing upwards), so the positive distances are inside, and
+    /// holes counter-clockwise.
+    ///
+    /// A contour is a hole, if it is enclosed by an odd number of other
+    /// contours. This fixes shapes, whose contours have inconsistent
+    /// directions, which would otherwise be filled wrongly by the non-zero
+    /// and positive fill rules.
+    pub fn normalize(&mut self) {
+        let reverse: Vec<bool> = {
+            let contours = self.contours();
+            contours
+                .iter()
+                .enumerate()
+                .map(|(i, contour)| {
+                    let depth = contours
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, other)| j != i && other.encloses(contour))
+                        .count();
+                    let winding = if depth % 2 == 0 { 1 } else { -1 };
+                    contour.winding() == -winding
+                })
+                .collect()
+        };
+        if !reverse.contains(&true) {
+            return;
+        }
+        for (range, _) in self
+            .contour_ranges()
+            .into_iter()
+            .zip(reverse)
+            .filter(|(_, reverse)| *reverse)
+        {
+            let edges = &mut self.edges[range];
+            edges.reverse();
+            for (i, edge) in edges.iter_mut().enumerate() {
+                *edge = Edge::new(edge.segment.reversed(), edge.color, i == 0);
+            }
+        }
+        self.update_bounds();
+    }
+
+    /// Returns the crossings of all edges with the horizontal line `y`
+    /// (unsorted).
+    pub fn scanline_intersections(&self, y: S) -> SmallVec<[Intersection<S>; 4]> {
         let mut intersections = SmallVec::new();
         if self.aabr.min.y > y || y > self.aabr.max.y {
             return intersections;
@@ -61,31 +204,261 @@ where
         if !aabr_potentialli_contains_circle(self.aabr, point, max_distance) {
             return None;
         }
-        let mut best = None;
-        for edge in &self.edges {
-            if aabr_potentialli_contains_circle(edge.aabr, point, max_distance) {
+        let mut best: Option<(S, Point2<S>, usize)> = None;
+        self.bvh
+            .visit(&self.edges, point, max_distance, |index, edge| {
                 let (distance, p) = edge.closest_point(point, epsilon);
-                if distance <= max_distance {
-                    if let Some((ref mut best_distance, ref mut best_point)) = best {
-                        if *best_distance > distance {
-                            *best_distance = distance;
-                            *best_point = p;
+                match best {
+                    Some((best_distance, _, best_index))
+                        if best_distance < distance
+                            || (best_distance == distance && best_index < index) => {}
+                    _ if distance <= max_distance => best = Some((distance, p, index)),
+                    _ => {}
+                }
+                best.map_or(max_distance, |(distance, _, _)| distance)
+            });
+        best.map(|(distance, p, _)| (distance, p))
+    }
+
+    /// Computes the signed pseudo-distance (the "perpendicular distance") of
+    /// `point` to the closest edge within `max_distance`.
+    ///
+    /// When the closest point lies on an endpoint of the edge, the edge is
+    /// extended tangentially beyond that endpoint, which keeps corners sharp.
+    pub fn closest_pseudo_distance(
+        &self,
+        point: Point2<S>,
+        max_distance: S,
+        epsilon: S,
+    ) -> Option<S> {
+        if !aabr_potentialli_contains_circle(self.aabr, point, max_distance) {
+            return None;
+        }
+        let mut best: Option<EdgeCandidate<S>> = None;
+        self.bvh
+            .visit(&self.edges, point, max_distance, |index, edge| {
+                let (distance, param) = edge.signed_distance(point, epsilon);
+                if distance.distance.abs() <= max_distance && replaces(&distance, index, &best) {
+                    best = Some((distance, index, param));
+                }
+                best.map_or(max_distance, |(distance, _, _)| distance.distance.abs())
+            });
+        best.map(|(mut distance, index, param)| {
+            self.edges[index].distance_to_pseudo_distance(&mut distance, point, param);
+            distance.distance
+        })
+    }
+
+    /// Computes the signed pseudo-distance of `point` for each color channel
+    /// (red, green, blue), taking only edges of the matching color into account.
+    pub fn multi_channel_distance(&self, point: Point2<S>, epsilon: S) -> [S; 3] {
+        let mut best: [Option<EdgeCandidate<S>>; 3] = [None, None, None];
+        let bound = |best: &[Option<EdgeCandidate<S>>; 3], color: EdgeColor| {
+            let mut bound = S::zero();
+            for (channel, best) in best.iter().enumerate() {
+                if color.has_channel(channel) {
+                    bound = match best {
+                        Some((distance, _, _)) => max(bound, distance.distance.abs()),
+                        None => S::max_value(),
+                    };
+                }
+            }
+            bound
+        };
+        self.bvh
+            .visit(&self.edges, point, S::max_value(), |index, edge| {
+                let edge_bound = bound(&best, edge.color);
+                if edge_bound > S::zero()
+                    && aabr_potentialli_contains_circle(edge.aabr, point, edge_bound)
+                {
+                    let (distance, param) = edge.signed_distance(point, epsilon);
+                    for (channel, best) in best.iter_mut().enumerate() {
+                        if edge.color.has_channel(channel) && replaces(&distance, index, best) {
+                            *best = Some((distance, index, param));
                         }
-                    } else {
-                        best = Some((distance, p));
                     }
                 }
+                bound(&best, EdgeColor::WHITE)
+            });
+        let mut result = [-S::max_value(); 3];
+        for (channel, best) in best.iter_mut().enumerate() {
+            if let Some((distance, index, param)) = best {
+                self.edges[*index].distance_to_pseudo_distance(distance, point, *param);
+                result[channel] = distance.distance;
             }
         }
-        best
+        result
     }
 }
 
-pub struct Edge<S> {
-    segment: EdgeSegment<S>,
-    color: EdgeColor,
+/// A closed sequence of edges of a shape.
+#[derive(Copy, Clone, Debug)]
+pub struct Contour<'a, S> {
+    edges: &'a [Edge<S>],
     aabr: Aabr<S>,
-    is_new_contour: bool,
+}
+
+impl<'a, S> Contour<'a, S>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    pub(crate) fn new(edges: &'a [Edge<S>]) -> Self {
+        let mut aabr = edges[0].aabr;
+        for edge in &edges[1..] {
+            aabr.expand_to_contain(edge.aabr);
+        }
+        Contour { edges, aabr }
+    }
+
+    #[inline]
+    pub fn edges(&self) -> &'a [Edge<S>] {
+        self.edges
+    }
+
+    #[inline]
+    pub fn aabr(&self) -> Aabr<S> {
+        self.aabr
+    }
+
+    /// Computes the area enclosed by the contour, which is positive if the
+    /// contour is oriented clockwise (with the y-axis pointing upwards), and
+    /// negative if counter-clockwise.
+    ///
+    /// The area of the curves is computed exactly.
+    pub fn signed_area(&self) -> S {
+        let ratio = |a: u16, b: u16| <S as From<u16>>::from(a) / <S as From<u16>>::from(b);
+        let mut total = S::zero();
+        for edge in self.edges {
+            // the integral of `cross(p, p')` along the edge, which is the
+            // doubled counter-clockwise area
+            total = total
+                + match edge.segment {
+                    EdgeSegment::Linear(e) => cross(e.start, e.end),
+                    EdgeSegment::Quadratic(e) => {
+                        ratio(2, 3) * (cross(e.start, e.ctrl) + cross(e.ctrl, e.end))
+                            + ratio(1, 3) * cross(e.start, e.end)
+                    }
+                    EdgeSegment::Cubic(e) => {
+                        ratio(3, 5) * (cross(e.start, e.ctrl0) + cross(e.ctrl1, e.end))
+                            + ratio(3, 10)
+                                * (cross(e.start, e.ctrl1)
+                                    + cross(e.ctrl0, e.ctrl1)
+                                    + cross(e.ctrl0, e.end))
+                            + ratio(1, 10) * cross(e.start, e.end)
+                    }
+                };
+        }
+        -total / (S::one() + S::one())
+    }
+
+    /// Returns `1` if the contour is oriented clockwise (with the y-axis
+    /// pointing upwards), `-1` if counter-clockwise and `0` if it has no
+    /// area.
+    pub fn winding(&self) -> i32 {
+        let area = self.signed_area();
+        if area > S::zero() {
+            1
+        } else if area < S::zero() {
+            -1
+        } else {
+            0
+        }
+    }
+
+    /// Returns the winding number of `point` with respect to this contour,
+    /// i.e. how often the contour runs clockwise around the point.
+    pub fn winding_number(&self, point: Point2<S>) -> i32 {
+        if !aabr_potentialli_contains_circle(self.aabr, point, S::zero()) {
+            return 0;
+        }
+        let mut winding = 0;
+        for edge in self.edges {
+            if edge.aabr.min.y <= point.y && edge.aabr.max.y >= point.y {
+                for intersection in edge.scanline_intersections(point.y) {
+                    if intersection.x <= point.x {
+                        winding += intersection.direction;
+                    }
+                }
+            }
+        }
+        winding
+    }
+
+    /// Whether `other` lies inside of this contour, tested at the middle of
+    /// its edges.
+    fn encloses(&self, other: &Contour<'_, S>) -> bool {
+        let half = S::one() / (S::one() + S::one());
+        self.aabr.contains_aabr(other.aabr)
+            && self.signed_area().abs() > other.signed_area().abs()
+            && other
+                .edges
+                .iter()
+                .all(|edge| self.winding_number(edge.evaluate(half)) != 0)
+    }
+}
+
+/// A signed distance to an edge, positive on the right-hand side of the edge.
+///
+/// `dot` is used to decide between equidistant edges (e.g. at corners): the
+/// smaller the value, the more orthogonal the point is to the edge.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SignedDistance<S> {
+    pub distance: S,
+    pub dot: S,
+}
+
+impl<S: Real> SignedDistance<S> {
+    #[inline]
+    pub fn is_closer_than(&self, other: &Self) -> bool {
+        let a = self.distance.abs();
+        let b = other.distance.abs();
+        a < b || (a == b && self.dot < other.dot)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Edge<S> {
+    pub(crate) segment: EdgeSegment<S>,
+    pub(crate) color: EdgeColor,
+    pub(crate) aabr: Aabr<S>,
+    pub(crate) is_new_contour: bool,
+}
+
+impl<S> Edge<S>
+where
+    S: Real + FloatConst + RelativeEq + From<u16>,
+{
+    #[inline]
+    pub(crate) fn new(segment: EdgeSegment<S>, color: EdgeColor, is_new_contour: bool) -> Self {
+        Edge {
+            aabr: segment.aabr(),
+            segment,
+            color,
+            is_new_contour,
+        }
+    }
+}
+
+impl<S: Copy> Edge<S> {
+    #[inline]
+    pub fn segment(&self) -> &EdgeSegment<S> {
+        &self.segment
+    }
+
+    #[inline]
+    pub fn color(&self) -> EdgeColor {
+        self.color
+    }
+
+    #[inline]
+    pub fn aabr(&self) -> Aabr<S> {
+        self.aabr
+    }
+
+    #[inline]
+    pub fn is_new_contour(&self) -> bool {
+        self.is_new_contour
+    }
 }
 
 impl<S> core::ops::Deref for Edge<S> {
@@ -96,6 +469,7 @@ impl<S> core::ops::Deref for Edge<S> {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum EdgeSegment<S> {
     Linear(LineSegment2<S>),
     Quadratic(QuadraticBezier2<S>),
@@ -125,7 +499,9 @@ trait Segment {
     type Scalar;
     fn aabr(&self) -> Aabr<Self::Scalar>;
     fn evaluate(&self, value: Self::Scalar) -> Point2<Self::Scalar>;
-    fn scanline_intersections(&self, y: Self::Scalar) -> SmallVec<[Self::Scalar; 3]>;
+    fn direction(&self, value: Self::Scalar) -> Vec2<Self::Scalar>;
+    fn scanline_intersections(&self, y: Self::Scalar) -> SmallVec<[Intersection<Self::Scalar>; 3]>;
+    fn closest_param(&self, point: Point2<Self::Scalar>, epsilon: Self::Scalar) -> Self::Scalar;
     fn closest_point(
         &self,
         point: Point2<Self::Scalar>,
@@ -149,27 +525,123 @@ where
         mix(self.start, self.end, value)
     }
     #[inline]
-    fn scanline_intersections(&self, y: Self::Scalar) -> SmallVec<[Self::Scalar; 3]> {
+    fn direction(&self, _value: Self::Scalar) -> Vec2<Self::Scalar> {
+        self.end - self.start
+    }
+    #[inline]
+    fn scanline_intersections(&self, y: Self::Scalar) -> SmallVec<[Intersection<S>; 3]> {
         let mut solution = SmallVec::new();
-        if self.start.y <= y && y < self.end.y || self.end.y <= y && y < self.start.y {
-            if let Some(value) = solve_linear(self.end.y - self.start.y, self.start.y - y) {
-                let x = mix(self.start.x, self.end.x, value);
-                solution.push(x);
-            }
+        let direction = if self.start.y <= y && y < self.end.y {
+            1
+        } else if self.end.y <= y && y < self.start.y {
+            -1
+        } else {
+            return solution;
+        };
+        if let Some(value) = solve_linear(self.end.y - self.start.y, self.start.y - y) {
+            let x = mix(self.start.x, self.end.x, value);
+            solution.push(Intersection { x, direction });
         }
         solution
     }
     #[inline]
+    fn closest_param(&self, point: Point2<Self::Scalar>, _epsilon: Self::Scalar) -> Self::Scalar {
+        let ab = self.end - self.start;
+        let length_sq = ab.dot(ab);
+        if length_sq <= S::zero() {
+            return S::zero();
+        }
+        let value = (point - self.start).dot(ab) / length_sq;
+        value.max(S::zero()).min(S::one())
+    }
+    #[inline]
     fn closest_point(
         &self,
         point: Point2<Self::Scalar>,
-        epsilon: Self::Scalar,
+        _epsilon: Self::Scalar,
     ) -> (Self::Scalar, Point2<Self::Scalar>) {
         let p = self.projected_point(point);
         (p.distance(point), p)
     }
 }
 
+/// Returns the crossings of a curve from `start` to `end` with the horizontal
+/// line `y`, where `roots` are the parameters, at which the curve is at `y`.
+///
+/// The curve is split at its vertical `extrema` (sorted parameters) into
+/// monotonic pieces, which count like lines: the lower end is included, the
+/// upper one isn't. So a contour, which passes through a vertex, is counted
+/// once, and a contour, which only touches the line, isn't counted at all,
+/// regardless of the precision of the roots.
+fn monotonic_crossings<S, F>(
+    start: Point2<S>,
+    end: Point2<S>,
+    extrema: &[S],
+    roots: &[S],
+    y: S,
+    evaluate: F,
+) -> SmallVec<[Intersection<S>; 3]>
+where
+    S: Real,
+    F: Fn(S) -> Point2<S>,
+{
+    let mut crossings = SmallVec::new();
+    let mut piece_start = (S::zero(), start.y);
+    let piece_ends = extrema
+        .iter()
+        .map(|&t| (t, evaluate(t).y))
+        .chain(core::iter::once((S::one(), end.y)));
+    for piece_end in piece_ends {
+        let ((t0, y0), (t1, y1)) = (piece_start, piece_end);
+        piece_start = piece_end;
+        let direction = if y0 <= y && y < y1 {
+            1
+        } else if y1 <= y && y < y0 {
+            -1
+        } else {
+            continue;
+        };
+        // the root, which is the closest to the piece
+        let outside = |t: S| max(S::zero(), max(t0 - t, t - t1));
+        let t = roots
+            .iter()
+            .copied()
+            .min_by(|a, b| {
+                outside(*a)
+                    .partial_cmp(&outside(*b))
+                    .unwrap_or(Ordering::Equal)
+            })
+            .map_or_else(
+                || {
+                    if (y0 - y).abs() < (y1 - y).abs() {
+                        t0
+                    } else {
+                        t1
+                    }
+                },
+                |t| max(t0, min(t, t1)),
+            );
+        crossings.push(Intersection {
+            x: evaluate(t).x,
+            direction,
+        });
+    }
+    crossings
+}
+
+/// Converts the precision `epsilon` (in shape units) into a precision of the
+/// curve parameter, for a curve with the (estimated) `length`.
+#[inline]
+fn param_epsilon<S: Real>(epsilon: S, length: S) -> S {
+    let _2 = S::one() + S::one();
+    let epsilon = if length > epsilon {
+        epsilon / length
+    } else {
+        epsilon
+    };
+    max(epsilon, S::epsilon() * _2)
+}
+
 impl<S> Segment for QuadraticBezier2<S>
 where
     S: Real + From<u16>,
@@ -180,13 +652,13 @@ where
         let mut aabr = Aabr::new_empty(self.start);
         aabr.expand_to_contain_point(self.end);
         let bot = (self.ctrl - self.start) - (self.end - self.ctrl);
-        if bot.x.abs() < S::epsilon() {
+        if bot.x.abs() > S::epsilon() {
             let value = (self.ctrl.x - self.start.x) / bot.x;
             if S::zero() <= value && value <= S::one() {
                 aabr.expand_to_contain_point(self.evaluate(value));
             }
         }
-        if bot.y.abs() < S::epsilon() {
+        if bot.y.abs() > S::epsilon() {
             let value = (self.ctrl.y - self.start.y) / bot.y;
             if S::zero() <= value && value <= S::one() {
                 aabr.expand_to_contain_point(self.evaluate(value));
@@ -198,22 +670,41 @@ where
     fn evaluate(&self, value: Self::Scalar) -> Point2<Self::Scalar> {
         QuadraticBezier2::evaluate(*self, value)
     }
+    #[inline]
+    fn direction(&self, value: Self::Scalar) -> Vec2<Self::Scalar> {
+        let direction = self.evaluate_derivative(value);
+        if direction.x == S::zero() && direction.y == S::zero() {
+            self.end - self.start
+        } else {
+            direction
+        }
+    }
 
     #[inline]
-    fn scanline_intersections(&self, y: Self::Scalar) -> SmallVec<[Self::Scalar; 3]> {
+    fn scanline_intersections(&self, y: Self::Scalar) -> SmallVec<[Intersection<S>; 3]> {
         let _2 = S::one() + S::one();
         let ba = self.ctrl - self.start;
         let cb2a = self.end - self.ctrl - ba;
         let b2a2 = ba * _2;
-        let solutions_values = solve_quadratic(cb2a.y, b2a2.y, self.start.y - y);
-        let mut solutions = SmallVec::new();
-        for value in solutions_values {
-            if value >= S::zero() && value < S::one() {
-                let x = cb2a.x * value * value + b2a2.x * value + self.start.x;
-                solutions.push(x);
+        let roots = solve_quadratic(cb2a.y, b2a2.y, self.start.y - y);
+        let mut extrema = SmallVec::<[S; 2]>::new();
+        if cb2a.y.abs() > S::epsilon() {
+            let value = -ba.y / cb2a.y;
+            if S::zero() < value && value < S::one() {
+                extrema.push(value);
             }
         }
-        solutions
+        monotonic_crossings(self.start, self.end, &extrema, &roots, y, |value| {
+            Segment::evaluate(self, value)
+        })
+    }
+
+    #[inline]
+    fn closest_param(&self, point: Point2<Self::Scalar>, epsilon: Self::Scalar) -> Self::Scalar {
+        let length = self.start.distance(self.ctrl) + self.ctrl.distance(self.end);
+        let epsilon = param_epsilon(epsilon, length);
+        let (v, _) = self.binary_search_point_by_steps(point, 1, epsilon);
+        v.max(S::zero()).min(S::one())
     }
 
     #[inline]
@@ -222,12 +713,7 @@ where
         point: Point2<Self::Scalar>,
         epsilon: Self::Scalar,
     ) -> (Self::Scalar, Point2<Self::Scalar>) {
-        let (v, mut p) = self.binary_search_point_by_steps(point, 1, epsilon);
-        if v < S::zero() {
-            p = self.start;
-        } else if v > S::one() {
-            p = self.end;
-        }
+        let p = Segment::evaluate(self, self.closest_param(point, epsilon));
         (p.distance(point), p)
     }
 }
@@ -262,28 +748,50 @@ where
     fn evaluate(&self, value: Self::Scalar) -> Point2<Self::Scalar> {
         CubicBezier2::evaluate(*self, value)
     }
+    #[inline]
+    fn direction(&self, value: Self::Scalar) -> Vec2<Self::Scalar> {
+        let direction = self.evaluate_derivative(value);
+        if direction.x == S::zero() && direction.y == S::zero() {
+            if value == S::zero() {
+                self.ctrl1 - self.start
+            } else if value == S::one() {
+                self.end - self.ctrl0
+            } else {
+                self.end - self.start
+            }
+        } else {
+            direction
+        }
+    }
 
     #[inline]
-    fn scanline_intersections(&self, y: Self::Scalar) -> SmallVec<[Self::Scalar; 3]> {
+    fn scanline_intersections(&self, y: Self::Scalar) -> SmallVec<[Intersection<S>; 3]> {
         let _3 = S::one() + S::one() + S::one();
         let ba = self.ctrl0 - self.start;
         let cb2a = self.ctrl1 - self.ctrl0 - ba;
         let b3a3 = ba * _3;
         let c3b6a3 = cb2a * _3;
         let dc3b3a = self.end - self.start + (self.ctrl0 - self.ctrl1) * _3;
-        let solutions_values = solve_cubic(dc3b3a.y, c3b6a3.y, b3a3.y, self.start.y - y);
-        let mut solutions = SmallVec::new();
-        for value in solutions_values {
-            if value >= S::zero() && value < S::one() {
-                let value_sq = value * value;
-                let x = dc3b3a.x * value_sq * value
-                    + c3b6a3.x * value_sq
-                    + b3a3.x * value
-                    + self.start.x;
-                solutions.push(x);
-            }
-        }
-        solutions
+        let roots = solve_cubic(dc3b3a.y, c3b6a3.y, b3a3.y, self.start.y - y);
+        // the roots of the derivative `3 * (dc3b3a * t^2 + 2 * cb2a * t + ba)`
+        let mut extrema: SmallVec<[S; 2]> = solve_quadratic(dc3b3a.y, cb2a.y + cb2a.y, ba.y)
+            .into_iter()
+            .filter(|&value| S::zero() < value && value < S::one())
+            .collect();
+        extrema.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        monotonic_crossings(self.start, self.end, &extrema, &roots, y, |value| {
+            Segment::evaluate(self, value)
+        })
+    }
+
+    #[inline]
+    fn closest_param(&self, point: Point2<Self::Scalar>, epsilon: Self::Scalar) -> Self::Scalar {
+        let length = self.start.distance(self.ctrl0)
+            + self.ctrl0.distance(self.ctrl1)
+            + self.ctrl1.distance(self.end);
+        let epsilon = param_epsilon(epsilon, length);
+        let (v, _) = self.binary_search_point_by_steps(point, 2, epsilon);
+        v.max(S::zero()).min(S::one())
     }
 
     #[inline]
@@ -292,12 +800,7 @@ where
         point: Point2<Self::Scalar>,
         epsilon: Self::Scalar,
     ) -> (Self::Scalar, Point2<Self::Scalar>) {
-        let (v, mut p) = self.binary_search_point_by_steps(point, 2, epsilon);
-        if v < S::zero() {
-            p = self.start;
-        } else if v > S::one() {
-            p = self.end;
-        }
+        let p = Segment::evaluate(self, self.closest_param(point, epsilon));
         (p.distance(point), p)
     }
 }
@@ -307,7 +810,7 @@ where
     S: Real + FloatConst + RelativeEq + From<u16>,
 {
     #[inline]
-    fn aabr(&self) -> Aabr<S> {
+    pub(crate) fn aabr(&self) -> Aabr<S> {
         match self {
             EdgeSegment::Linear(e) => Segment::aabr(e),
             EdgeSegment::Quadratic(e) => Segment::aabr(e),
@@ -324,6 +827,24 @@ where
         }
     }
 
+    #[inline]
+    pub fn direction(&self, value: S) -> Vec2<S> {
+        match self {
+            EdgeSegment::Linear(e) => Segment::direction(e, value),
+            EdgeSegment::Quadratic(e) => Segment::direction(e, value),
+            EdgeSegment::Cubic(e) => Segment::direction(e, value),
+        }
+    }
+
+    #[inline]
+    pub fn start(&self) -> Point2<S> {
+        match self {
+            EdgeSegment::Linear(e) => e.start,
+            EdgeSegment::Quadratic(e) => e.start,
+            EdgeSegment::Cubic(e) => e.start,
+        }
+    }
+
     #[inline]
     pub fn end(&self) -> Point2<S> {
         match self {
@@ -334,7 +855,7 @@ where
     }
 
     #[inline]
-    fn scanline_intersections(&self, y: S) -> SmallVec<[S; 3]> {
+    fn scanline_intersections(&self, y: S) -> SmallVec<[Intersection<S>; 3]> {
         match self {
             EdgeSegment::Linear(e) => e.scanline_intersections(y),
             EdgeSegment::Quadratic(e) => e.scanline_intersections(y),
@@ -349,8 +870,215 @@ where
             EdgeSegment::Cubic(e) => e.closest_point(point, epsilon),
         }
     }
+
+    #[inline]
+    pub(crate) fn closest_param(&self, point: Point2<S>, epsilon: S) -> S {
+        match self {
+            EdgeSegment::Linear(e) => e.closest_param(point, epsilon),
+            EdgeSegment::Quadratic(e) => e.closest_param(point, epsilon),
+            EdgeSegment::Cubic(e) => e.closest_param(point, epsilon),
+        }
+    }
+
+    /// Returns this segment with `transform` applied to its points.
+    pub fn transformed(&self, transform: &Transform<S>) -> EdgeSegment<S> {
+        let t = |p| transform.transform_point(p);
+        match self {
+            EdgeSegment::Linear(e) => EdgeSegment::Linear(LineSegment2 {
+                start: t(e.start),
+                end: t(e.end),
+            }),
+            EdgeSegment::Quadratic(e) => EdgeSegment::Quadratic(QuadraticBezier2 {
+                start: t(e.start),
+                ctrl: t(e.ctrl),
+                end: t(e.end),
+            }),
+            EdgeSegment::Cubic(e) => EdgeSegment::Cubic(CubicBezier2 {
+                start: t(e.start),
+                ctrl0: t(e.ctrl0),
+                ctrl1: t(e.ctrl1),
+                end: t(e.end),
+            }),
+        }
+    }
+
+    /// Splits this segment at the parameter `value` into two parts.
+    pub fn split(&self, value: S) -> [EdgeSegment<S>; 2] {
+        match self {
+            EdgeSegment::Linear(e) => {
+                let middle = e.evaluate(value);
+                [
+                    LineSegment2 {
+                        start: e.start,
+                        end: middle,
+                    }
+                    .into(),
+                    LineSegment2 {
+                        start: middle,
+                        end: e.end,
+                    }
+                    .into(),
+                ]
+            }
+            EdgeSegment::Quadratic(e) => {
+                let [first, last] = e.split(value);
+                [first.into(), last.into()]
+            }
+            EdgeSegment::Cubic(e) => {
+                let [first, last] = e.split(value);
+                [first.into(), last.into()]
+            }
+        }
+    }
+
+    /// Returns this segment with the opposite direction.
+    pub fn reversed(&self) -> EdgeSegment<S> {
+        match *self {
+            EdgeSegment::Linear(e) => LineSegment2 {
+                start: e.end,
+                end: e.start,
+            }
+            .into(),
+            EdgeSegment::Quadratic(e) => e.reversed().into(),
+            EdgeSegment::Cubic(e) => e.reversed().into(),
+        }
+    }
+
+    /// Splits this segment into three parts of equal parameter length.
+    pub fn split_in_thirds(&self) -> [EdgeSegment<S>; 3] {
+        let _3 = S::one() + S::one() + S::one();
+        let third = S::one() / _3;
+        let half = S::one() / (S::one() + S::one());
+        match self {
+            EdgeSegment::Linear(e) => {
+                let a = e.evaluate(third);
+                let b = e.evaluate(third + third);
+                [
+                    LineSegment2 {
+                        start: e.start,
+                        end: a,
+                    }
+                    .into(),
+                    LineSegment2 { start: a, end: b }.into(),
+                    LineSegment2 {
+                        start: b,
+                        end: e.end,
+                    }
+                    .into(),
+                ]
+            }
+            EdgeSegment::Quadratic(e) => {
+                let [first, rest] = e.split(third);
+                let [second, last] = rest.split(half);
+                [first.into(), second.into(), last.into()]
+            }
+            EdgeSegment::Cubic(e) => {
+                let [first, rest] = e.split(third);
+                let [second, last] = rest.split(half);
+                [first.into(), second.into(), last.into()]
+            }
+        }
+    }
+
+    /// Returns the signed distance from `point` to this segment and the
+    /// parameter of the closest point on the segment.
+    pub fn signed_distance(&self, point: Point2<S>, epsilon: S) -> (SignedDistance<S>, S) {
+        let param = self.closest_param(point, epsilon);
+        let closest = self.evaluate(param);
+        let direction = self.direction(param);
+        let offset = closest - point;
+        let distance = offset.magnitude();
+        let dot = if param <= S::zero() || param >= S::one() {
+            if distance > S::zero() {
+                direction.normalized().dot(offset / distance).abs()
+            } else {
+                S::zero()
+            }
+        } else {
+            S::zero()
+        };
+        let distance = SignedDistance {
+            distance: non_zero_sign(cross(direction, offset)) * distance,
+            dot,
+        };
+        (distance, param)
+    }
+
+    /// Converts a signed distance, which was computed by `signed_distance`,
+    /// into a pseudo-distance by extending the segment tangentially beyond
+    /// its endpoints.
+    pub fn distance_to_pseudo_distance(
+        &self,
+        distance: &mut SignedDistance<S>,
+        point: Point2<S>,
+        param: S,
+    ) {
+        let (endpoint, direction) = if param <= S::zero() {
+            (self.start(), self.direction(S::zero()))
+        } else if param >= S::one() {
+            (self.end(), self.direction(S::one()))
+        } else {
+            return;
+        };
+        let direction = direction.normalized();
+        let aq = point - endpoint;
+        let ts = aq.dot(direction);
+        if (param <= S::zero() && ts < S::zero()) || (param >= S::one() && ts > S::zero()) {
+            let pseudo_distance = cross(aq, direction);
+            if pseudo_distance.abs() <= distance.distance.abs() {
+                distance.distance = pseudo_distance;
+                distance.dot = S::zero();
+            }
+        }
+    }
 }
 
+/// A crossing of an edge with a horizontal line.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Intersection<S> {
+    pub x: S,
+    /// `1`, if the edge crosses the line upwards, `-1` if downwards.
+    pub direction: i32,
+}
+
+/// The rule, which decides whether a point is inside of a shape.
+///
+/// The rules look at the winding number of the point: the number of edges
+/// left of the point, which cross its horizontal line upwards, minus the ones
+/// crossing it downwards.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FillRule {
+    /// Inside, if the contours wind around the point (the default of SVG
+    /// and fonts).
+    NonZero,
+    /// Inside, if a ray from the point crosses an odd number of edges.
+    EvenOdd,
+    /// Inside, if the winding number is positive, i.e. the contours run
+    /// clockwise around the point (in a coordinate system with the y-axis
+    /// pointing upwards, like the outlines of TrueType fonts).
+    Positive,
+}
+
+impl Default for FillRule {
+    #[inline]
+    fn default() -> Self {
+        FillRule::NonZero
+    }
+}
+
+impl FillRule {
+    /// Whether a point with the given winding number is inside.
+    #[inline]
+    pub fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding & 1 != 0,
+            FillRule::Positive => winding > 0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum EdgeColor {
     BLACK = 0,
     RED = 1,
@@ -362,12 +1090,82 @@ pub enum EdgeColor {
     WHITE = 7,
 }
 
+impl EdgeColor {
+    #[inline]
+    pub fn bits(self) -> u8 {
+        self as u8
+    }
+
+    #[inline]
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & 7 {
+            0 => EdgeColor::BLACK,
+            1 => EdgeColor::RED,
+            2 => EdgeColor::GREEN,
+            3 => EdgeColor::YELLOW,
+            4 => EdgeColor::BLUE,
+            5 => EdgeColor::MAGENTA,
+            6 => EdgeColor::CYAN,
+            _ => EdgeColor::WHITE,
+        }
+    }
+
+    /// Returns `true` if this color contributes to the given channel
+    /// (`0` = red, `1` = green, `2` = blue).
+    #[inline]
+    pub fn has_channel(self, channel: usize) -> bool {
+        self.bits() & (1 << channel) != 0
+    }
+}
+
+impl core::ops::BitAnd for EdgeColor {
+    type Output = EdgeColor;
+    #[inline]
+    fn bitand(self, rhs: EdgeColor) -> EdgeColor {
+        EdgeColor::from_bits(self.bits() & rhs.bits())
+    }
+}
+
+impl core::ops::BitOr for EdgeColor {
+    type Output = EdgeColor;
+    #[inline]
+    fn bitor(self, rhs: EdgeColor) -> EdgeColor {
+        EdgeColor::from_bits(self.bits() | rhs.bits())
+    }
+}
+
+impl core::ops::BitXor for EdgeColor {
+    type Output = EdgeColor;
+    #[inline]
+    fn bitxor(self, rhs: EdgeColor) -> EdgeColor {
+        EdgeColor::from_bits(self.bits() ^ rhs.bits())
+    }
+}
+
+impl core::ops::Not for EdgeColor {
+    type Output = EdgeColor;
+    #[inline]
+    fn not(self) -> EdgeColor {
+        EdgeColor::from_bits(!self.bits())
+    }
+}
+
 pub struct ShapeBuilder<S> {
     edges: Vec<Edge<S>>,
     contour_start: Option<Point2<S>>,
     contour_previous: Option<Point2<S>>,
 }
 
+impl<S> Default for ShapeBuilder<S>
+where
+    S: Real + RelativeEq + FloatConst + From<u16>,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<S> ShapeBuilder<S>
 where
     S: Real + RelativeEq + FloatConst + From<u16>,
@@ -383,31 +1181,20 @@ where
     #[inline]
     pub fn finish(mut self) -> Option<Shape<S>> {
         self.close();
-        let mut edges = self.edges.iter();
-        if let Some(edge) = edges.next() {
-            let mut aabr = edge.aabr;
-            while let Some(edge) = edges.next() {
-                aabr.expand_to_contain(edge.aabr());
-            }
-            Some(Shape {
-                edges: self.edges,
-                aabr,
-            })
-        } else {
-            None
-        }
+        Shape::from_edges(self.edges)
     }
     #[inline]
     pub fn close(&mut self) -> &mut Self {
-        if let Some(contour_previous) = self.contour_previous.take() {
+        if let Some(contour_previous) = self.contour_previous {
             let contour_start = self.contour_start.unwrap();
             if contour_previous != contour_start {
-                // close line
+                // close line, which still belongs to the current contour
                 self.push_edge(LineSegment2 {
                     start: contour_previous,
                     end: contour_start,
                 });
             }
+            self.contour_previous = None;
         }
         self
     }
@@ -421,15 +1208,10 @@ where
     #[inline]
     fn push_edge(&mut self, edge: impl Into<EdgeSegment<S>>) -> &mut Self {
         let segment = edge.into();
-        let aabr = segment.aabr();
         let is_new_contour = self.contour_previous.is_none();
         let end = segment.end();
-        self.edges.push(Edge {
-            segment: segment,
-            color: EdgeColor::WHITE,
-            aabr,
-            is_new_contour,
-        });
+        self.edges
+            .push(Edge::new(segment, EdgeColor::WHITE, is_new_contour));
         self.contour_previous = Some(end);
         self
     }
@@ -471,3 +1253,78 @@ where
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use core::f64::consts::PI;
+
+    use super::*;
+    use crate::svg::parse_path;
+
+    #[test]
+    fn close_keeps_the_closing_line_in_the_contour() {
+        let mut builder = Shape::<f64>::builder();
+        builder
+            .move_to(0.0, 0.0)
+            .line_to(1.0, 0.0)
+            .line_to(0.0, 1.0);
+        builder.close();
+        let shape = builder.finish().unwrap();
+        assert_eq!(shape.edges.len(), 3);
+        let contours = shape.edges.iter().filter(|e| e.is_new_contour).count();
+        assert_eq!(contours, 1);
+        assert!(shape.edges[0].is_new_contour);
+    }
+
+    fn signed_areas(shape: &Shape<f64>) -> Vec<f64> {
+        shape.contours().iter().map(|c| c.signed_area()).collect()
+    }
+
+    fn windings(shape: &Shape<f64>) -> Vec<i32> {
+        shape.contours().iter().map(|c| c.winding()).collect()
+    }
+
+    #[test]
+    fn signed_area_of_lines_and_curves() {
+        let squares = parse_path("M 0 0 L 0 1 L 1 1 L 1 0 Z M 3 0 L 4 0 L 4 1 L 3 1 Z").unwrap();
+        assert_eq!(signed_areas(&squares), [1.0, -1.0]);
+        assert_eq!(windings(&squares), [1, -1]);
+
+        // a parabolic segment has 2/3 of the area of its bounding box
+        let arch = parse_path("M 0 0 Q 1 2 2 0 Z").unwrap();
+        assert_relative_eq!(signed_areas(&arch)[0], 4.0 / 3.0, epsilon = 1e-12);
+
+        // a circle of four cubic curves, counter-clockwise
+        let k = 0.552_284_749_8;
+        let circle = parse_path(&alloc::format!(
+            "M 1 0 C 1 {k} {k} 1 0 1 C -{k} 1 -1 {k} -1 0 \
+             C -1 -{k} -{k} -1 0 -1 C {k} -1 1 -{k} 1 0 Z",
+            k = k
+        ))
+        .unwrap();
+        assert_relative_eq!(signed_areas(&circle)[0], -PI, epsilon = 1e-3);
+        assert_eq!(windings(&circle), [-1]);
+    }
+
+    #[test]
+    fn normalize_orients_holes() {
+        // both clockwise
+        let mut shape = parse_path("M 0 0 L 0 4 L 4 4 L 4 0 Z M 1 1 L 1 3 L 3 3 L 3 1 Z").unwrap();
+        assert_eq!(windings(&shape), [1, 1]);
+        shape.normalize();
+        assert_eq!(windings(&shape), [1, -1]);
+        assert_eq!(signed_areas(&shape), [16.0, -4.0]);
+
+        // both counter-clockwise, with an island in the hole
+        let mut shape = parse_path(
+            "M 0 0 L 6 0 L 6 6 L 0 6 Z M 1 1 L 5 1 L 5 5 L 1 5 Z M 2 2 L 4 2 L 4 4 L 2 4 Z",
+        )
+        .unwrap();
+        assert_eq!(windings(&shape), [-1, -1, -1]);
+        shape.normalize();
+        assert_eq!(windings(&shape), [1, -1, 1]);
+        let contours = shape.edges.iter().filter(|e| e.is_new_contour).count();
+        assert_eq!(contours, 3);
+    }
+}
@@ -0,0 +1,1405 @@
+//! Parsing of SVG path data (the `d` attribute of a `<path>`).
+//!
+//! Coordinates are used as they are, so the y-axis points downwards.
+//! Elliptical arcs are approximated by cubic segments.
+//!
+//! With the `roxmltree` feature, whole SVG documents can be imported as well
+//! (see `parse_document`).
+
+#[cfg(feature = "roxmltree")]
+use alloc::vec::Vec;
+use approx::RelativeEq;
+use core::fmt;
+use num_traits::{real::Real, FloatConst, NumCast};
+
+#[cfg(feature = "roxmltree")]
+use crate::{
+    boolean::union_all,
+    shape::{Aabr, FillRule},
+    transform::Transform,
+};
+use crate::{
+    math::{cross, max},
+    shape::{Point2, Shape, ShapeBuilder},
+};
+
+/// The kind of a `PathError`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PathErrorKind {
+    /// The path data contains an unknown command.
+    UnknownCommand(char),
+    /// A command is missing one of its numbers.
+    ExpectedNumber,
+    /// An arc is missing one of its flags (`0` or `1`).
+    ExpectedFlag,
+    /// The path data doesn't start with a move command.
+    ExpectedMove,
+    /// The path data contains no edges.
+    Empty,
+    /// A transform list contains an unknown transform function.
+    UnknownTransform,
+    /// A transform function is missing its opening or closing parenthesis.
+    ExpectedParenthesis,
+    /// A transform function has the wrong number of arguments.
+    ArgumentCount,
+}
+
+/// An error while parsing path data.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PathError {
+    pub kind: PathErrorKind,
+    /// The position of the error in the path data (in bytes).
+    pub offset: usize,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            PathErrorKind::UnknownCommand(c) => write!(f, "unknown path command `{}`", c)?,
+            PathErrorKind::ExpectedNumber => f.write_str("expected a number")?,
+            PathErrorKind::ExpectedFlag => f.write_str("expected a flag")?,
+            PathErrorKind::ExpectedMove => f.write_str("path data has to start with a move")?,
+            PathErrorKind::Empty => f.write_str("path data contains no edges")?,
+            PathErrorKind::UnknownTransform => f.write_str("unknown transform function")?,
+            PathErrorKind::ExpectedParenthesis => f.write_str("expected a parenthesis")?,
+            PathErrorKind::ArgumentCount => f.write_str("wrong number of arguments")?,
+        }
+        write!(f, " at byte {}", self.offset)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PathError {}
+
+struct Lexer<'a> {
+    data: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn error(&self, kind: PathErrorKind) -> PathError {
+        PathError {
+            kind,
+            offset: self.pos,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        let bytes = self.data.as_bytes();
+        while self.pos < bytes.len()
+            && (bytes[self.pos].is_ascii_whitespace() || bytes[self.pos] == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_separators();
+        self.data.as_bytes().get(self.pos).copied()
+    }
+
+    fn at_number(&mut self) -> bool {
+        matches!(self.peek(), Some(b'0'..=b'9' | b'-' | b'+' | b'.'))
+    }
+
+    fn command(&mut self) -> Option<char> {
+        self.skip_separators();
+        let c = self.data[self.pos..].chars().next()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn number<S: Real>(&mut self) -> Result<S, PathError> {
+        self.skip_separators();
+        let bytes = self.data.as_bytes();
+        let start = self.pos;
+        let mut end = start;
+        if matches!(bytes.get(end), Some(b'-' | b'+')) {
+            end += 1;
+        }
+        let mut has_dot = false;
+        while let Some(&c) = bytes.get(end) {
+            if c.is_ascii_digit() {
+                end += 1;
+            } else if c == b'.' && !has_dot {
+                has_dot = true;
+                end += 1;
+            } else {
+                break;
+            }
+        }
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            let mut exp_end = end + 1;
+            if matches!(bytes.get(exp_end), Some(b'-' | b'+')) {
+                exp_end += 1;
+            }
+            if matches!(bytes.get(exp_end), Some(b'0'..=b'9')) {
+                while matches!(bytes.get(exp_end), Some(b'0'..=b'9')) {
+                    exp_end += 1;
+                }
+                end = exp_end;
+            }
+        }
+        let value = self.data[start..end]
+            .parse::<f64>()
+            .ok()
+            .and_then(NumCast::from)
+            .ok_or_else(|| self.error(PathErrorKind::ExpectedNumber))?;
+        self.pos = end;
+        Ok(value)
+    }
+
+    fn flag(&mut self) -> Result<bool, PathError> {
+        // flags don't need to be separated from the following number
+        match self.peek() {
+            Some(b'0') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(b'1') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            _ => Err(self.error(PathErrorKind::ExpectedFlag)),
+        }
+    }
+
+    fn point<S: Real>(&mut self) -> Result<Point2<S>, PathError> {
+        let x = self.number()?;
+        let y = self.number()?;
+        Ok(Point2::new(x, y))
+    }
+}
+
+/// Receives the segments of parsed paths.
+trait PathSink<S> {
+    fn move_to(&mut self, to: Point2<S>);
+    fn line_to(&mut self, to: Point2<S>);
+    fn quadratic_to(&mut self, ctrl: Point2<S>, to: Point2<S>);
+    fn cubic_to(&mut self, ctrl0: Point2<S>, ctrl1: Point2<S>, to: Point2<S>);
+    fn close(&mut self);
+}
+
+impl<S> PathSink<S> for ShapeBuilder<S>
+where
+    S: Real + RelativeEq + FloatConst + From<u16>,
+{
+    #[inline]
+    fn move_to(&mut self, to: Point2<S>) {
+        ShapeBuilder::move_to(self, to.x, to.y);
+    }
+    #[inline]
+    fn line_to(&mut self, to: Point2<S>) {
+        ShapeBuilder::line_to(self, to.x, to.y);
+    }
+    #[inline]
+    fn quadratic_to(&mut self, ctrl: Point2<S>, to: Point2<S>) {
+        ShapeBuilder::quadratic_to(self, ctrl.x, ctrl.y, to.x, to.y);
+    }
+    #[inline]
+    fn cubic_to(&mut self, ctrl0: Point2<S>, ctrl1: Point2<S>, to: Point2<S>) {
+        ShapeBuilder::cubic_to(self, ctrl0.x, ctrl0.y, ctrl1.x, ctrl1.y, to.x, to.y);
+    }
+    #[inline]
+    fn close(&mut self) {
+        ShapeBuilder::close(self);
+    }
+}
+
+/// Approximates the elliptical arc from `from` to `to` by cubic segments of
+/// at most a quarter turn (see the implementation notes of the SVG spec).
+fn arc_to<S>(
+    builder: &mut impl PathSink<S>,
+    from: Point2<S>,
+    radii: Point2<S>,
+    rotation: S,
+    (large_arc, sweep): (bool, bool),
+    to: Point2<S>,
+) where
+    S: Real + RelativeEq + FloatConst + From<u16>,
+{
+    let (mut rx, mut ry) = (radii.x.abs(), radii.y.abs());
+    if from == to {
+        return;
+    }
+    if rx <= S::epsilon() || ry <= S::epsilon() {
+        builder.line_to(to);
+        return;
+    }
+    let _2 = S::one() + S::one();
+    let _3 = _2 + S::one();
+    let _4 = _2 + _2;
+    let (sin_phi, cos_phi) = rotation.to_radians().sin_cos();
+
+    // the start point in the coordinate system of the ellipse
+    let half = (from - to) / _2;
+    let p = Point2::new(
+        cos_phi * half.x + sin_phi * half.y,
+        -sin_phi * half.x + cos_phi * half.y,
+    );
+    // scale up radii, which are too small
+    let lambda = (p.x * p.x) / (rx * rx) + (p.y * p.y) / (ry * ry);
+    if lambda > S::one() {
+        let scale = lambda.sqrt();
+        rx = rx * scale;
+        ry = ry * scale;
+    }
+    let (rx2, ry2) = (rx * rx, ry * ry);
+    let numerator = rx2 * ry2 - rx2 * p.y * p.y - ry2 * p.x * p.x;
+    let denominator = rx2 * p.y * p.y + ry2 * p.x * p.x;
+    let mut coefficient = max(S::zero(), numerator / denominator).sqrt();
+    if large_arc == sweep {
+        coefficient = -coefficient;
+    }
+    let c = Point2::new(coefficient * rx * p.y / ry, -coefficient * ry * p.x / rx);
+    let center = Point2::new(cos_phi * c.x - sin_phi * c.y, sin_phi * c.x + cos_phi * c.y)
+        + (from + to) / _2;
+
+    let angle = |a: Point2<S>, b: Point2<S>| cross(a, b).atan2(a.dot(b));
+    let u = Point2::new((p.x - c.x) / rx, (p.y - c.y) / ry);
+    let v = Point2::new((-p.x - c.x) / rx, (-p.y - c.y) / ry);
+    let start_angle = angle(Point2::unit_x(), u);
+    let mut sweep_angle = angle(u, v);
+    if !sweep && sweep_angle > S::zero() {
+        sweep_angle = sweep_angle - _2 * S::PI();
+    } else if sweep && sweep_angle < S::zero() {
+        sweep_angle = sweep_angle + _2 * S::PI();
+    }
+
+    let segments = (sweep_angle.abs() / S::FRAC_PI_2() - S::epsilon())
+        .ceil()
+        .max(S::one());
+    let delta = sweep_angle / segments;
+    let k = _4 / _3 * (delta / _4).tan();
+    let point = |theta: S| {
+        let (sin, cos) = theta.sin_cos();
+        center
+            + Point2::new(
+                rx * cos_phi * cos - ry * sin_phi * sin,
+                rx * sin_phi * cos + ry * cos_phi * sin,
+            )
+    };
+    let derivative = |theta: S| {
+        let (sin, cos) = theta.sin_cos();
+        Point2::new(
+            -rx * cos_phi * sin - ry * sin_phi * cos,
+            -rx * sin_phi * sin + ry * cos_phi * cos,
+        )
+    };
+    let segments = segments.to_usize().unwrap_or(1);
+    let mut theta = start_angle;
+    let mut start = from;
+    for i in 0..segments {
+        let next = theta + delta;
+        let end = if i + 1 == segments { to } else { point(next) };
+        let ctrl0 = start + derivative(theta) * k;
+        let ctrl1 = end - derivative(next) * k;
+        builder.cubic_to(ctrl0, ctrl1, end);
+        theta = next;
+        start = end;
+    }
+}
+
+/// Parses the path `data` and adds its contours to `builder`.
+///
+/// Supports all (absolute and relative) commands of SVG paths: `M`, `L`,
+/// `H`, `V`, `C`, `S`, `Q`, `T`, `A` and `Z`.
+pub fn build_path<S>(data: &str, builder: &mut ShapeBuilder<S>) -> Result<(), PathError>
+where
+    S: Real + RelativeEq + FloatConst + From<u16>,
+{
+    build_path_into(data, builder)
+}
+
+fn build_path_into<S>(data: &str, builder: &mut impl PathSink<S>) -> Result<(), PathError>
+where
+    S: Real + RelativeEq + FloatConst + From<u16>,
+{
+    let mut lexer = Lexer { data, pos: 0 };
+    let mut current = Point2::<S>::zero();
+    let mut start = current;
+    // the last control point of a cubic or quadratic command, for reflection
+    let mut last_cubic: Option<Point2<S>> = None;
+    let mut last_quadratic: Option<Point2<S>> = None;
+    let mut first = true;
+    let _2 = S::one() + S::one();
+    loop {
+        lexer.skip_separators();
+        let offset = lexer.pos;
+        let command = match lexer.command() {
+            Some(command) => command,
+            None => break,
+        };
+        if first && !command.eq_ignore_ascii_case(&'M') {
+            return Err(PathError {
+                kind: PathErrorKind::ExpectedMove,
+                offset,
+            });
+        }
+        first = false;
+        let relative = command.is_ascii_lowercase();
+        let origin = |current: Point2<S>| if relative { current } else { Point2::zero() };
+        let (mut next_cubic, mut next_quadratic) = (None, None);
+        match command.to_ascii_uppercase() {
+            'M' => {
+                current = origin(current) + lexer.point()?;
+                start = current;
+                builder.move_to(current);
+                // following pairs are implicit line commands
+                while lexer.at_number() {
+                    current = origin(current) + lexer.point()?;
+                    builder.line_to(current);
+                }
+            }
+            'L' => loop {
+                current = origin(current) + lexer.point()?;
+                builder.line_to(current);
+                if !lexer.at_number() {
+                    break;
+                }
+            },
+            'H' => loop {
+                current.x = origin(current).x + lexer.number()?;
+                builder.line_to(current);
+                if !lexer.at_number() {
+                    break;
+                }
+            },
+            'V' => loop {
+                current.y = origin(current).y + lexer.number()?;
+                builder.line_to(current);
+                if !lexer.at_number() {
+                    break;
+                }
+            },
+            'C' | 'S' => {
+                let smooth = command.eq_ignore_ascii_case(&'S');
+                let mut last = last_cubic;
+                loop {
+                    let o = origin(current);
+                    let ctrl0 = if smooth {
+                        last.map_or(current, |last| current * _2 - last)
+                    } else {
+                        o + lexer.point()?
+                    };
+                    let ctrl1 = o + lexer.point()?;
+                    current = o + lexer.point()?;
+                    builder.cubic_to(ctrl0, ctrl1, current);
+                    last = Some(ctrl1);
+                    if !lexer.at_number() {
+                        break;
+                    }
+                }
+                next_cubic = last;
+            }
+            'Q' | 'T' => {
+                let smooth = command.eq_ignore_ascii_case(&'T');
+                let mut last = last_quadratic;
+                loop {
+                    let o = origin(current);
+                    let ctrl = if smooth {
+                        last.map_or(current, |last| current * _2 - last)
+                    } else {
+                        o + lexer.point()?
+                    };
+                    current = o + lexer.point()?;
+                    builder.quadratic_to(ctrl, current);
+                    last = Some(ctrl);
+                    if !lexer.at_number() {
+                        break;
+                    }
+                }
+                next_quadratic = last;
+            }
+            'A' => loop {
+                let radii = lexer.point()?;
+                let rotation = lexer.number()?;
+                let flags = (lexer.flag()?, lexer.flag()?);
+                let end = origin(current) + lexer.point()?;
+                arc_to(builder, current, radii, rotation, flags, end);
+                current = end;
+                if !lexer.at_number() {
+                    break;
+                }
+            },
+            'Z' => {
+                builder.close();
+                current = start;
+            }
+            _ => {
+                return Err(PathError {
+                    kind: PathErrorKind::UnknownCommand(command),
+                    offset,
+                })
+            }
+        }
+        last_cubic = next_cubic;
+        last_quadratic = next_quadratic;
+    }
+    Ok(())
+}
+
+/// Parses the path `data` into a new `Shape`.
+pub fn parse_path<S>(data: &str) -> Result<Shape<S>, PathError>
+where
+    S: Real + RelativeEq + FloatConst + From<u16>,
+{
+    let mut builder = Shape::builder();
+    build_path(data, &mut builder)?;
+    builder.finish().ok_or(PathError {
+        kind: PathErrorKind::Empty,
+        offset: data.len(),
+    })
+}
+
+/// Applies a transformation to the segments before passing them on.
+#[cfg(feature = "roxmltree")]
+struct Transformed<'a, S> {
+    builder: &'a mut ShapeBuilder<S>,
+    transform: Transform<S>,
+}
+
+#[cfg(feature = "roxmltree")]
+impl<'a, S> PathSink<S> for Transformed<'a, S>
+where
+    S: Real + RelativeEq + FloatConst + From<u16>,
+{
+    #[inline]
+    fn move_to(&mut self, to: Point2<S>) {
+        PathSink::move_to(self.builder, self.transform.transform_point(to));
+    }
+    #[inline]
+    fn line_to(&mut self, to: Point2<S>) {
+        PathSink::line_to(self.builder, self.transform.transform_point(to));
+    }
+    #[inline]
+    fn quadratic_to(&mut self, ctrl: Point2<S>, to: Point2<S>) {
+        let t = &self.transform;
+        PathSink::quadratic_to(self.builder, t.transform_point(ctrl), t.transform_point(to));
+    }
+    #[inline]
+    fn cubic_to(&mut self, ctrl0: Point2<S>, ctrl1: Point2<S>, to: Point2<S>) {
+        let t = &self.transform;
+        PathSink::cubic_to(
+            self.builder,
+            t.transform_point(ctrl0),
+            t.transform_point(ctrl1),
+            t.transform_point(to),
+        );
+    }
+    #[inline]
+    fn close(&mut self) {
+        PathSink::close(self.builder);
+    }
+}
+
+/// An error while importing an SVG document.
+#[cfg(feature = "roxmltree")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum SvgError {
+    /// The document is no well-formed XML.
+    Xml(roxmltree::Error),
+    /// The path data of a `<path>` element is invalid.
+    Path {
+        line: u32,
+        column: u32,
+        error: PathError,
+    },
+    /// The `transform` attribute of an element is invalid.
+    Transform {
+        line: u32,
+        column: u32,
+        error: PathError,
+    },
+    /// An attribute has an invalid or unsupported value.
+    Attribute {
+        line: u32,
+        column: u32,
+        name: &'static str,
+    },
+    /// The document contains no filled shapes.
+    Empty,
+}
+
+#[cfg(feature = "roxmltree")]
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvgError::Xml(error) => error.fmt(f),
+            SvgError::Path {
+                line,
+                column,
+                error,
+            } => write!(f, "{}:{}: invalid path: {}", line, column, error),
+            SvgError::Transform {
+                line,
+                column,
+                error,
+            } => write!(f, "{}:{}: invalid transform: {}", line, column, error),
+            SvgError::Attribute { line, column, name } => {
+                write!(f, "{}:{}: invalid value of `{}`", line, column, name)
+            }
+            SvgError::Empty => f.write_str("the document contains no filled shapes"),
+        }
+    }
+}
+
+#[cfg(all(feature = "roxmltree", feature = "std"))]
+impl std::error::Error for SvgError {}
+
+/// The shapes of an SVG document, combined into a single `Shape`.
+#[cfg(feature = "roxmltree")]
+#[derive(Clone, Debug)]
+pub struct SvgShape<S> {
+    pub shape: Shape<S>,
+    /// The fill rule of `shape`, which can be passed on to
+    /// `Rasterizer::with_fill_rule`: the one of the element, if the document
+    /// has a single filled element, and `FillRule::NonZero` otherwise.
+    pub fill_rule: FillRule,
+    /// The `viewBox` of the root element (or its `width` and `height`).
+    pub view_box: Option<Aabr<S>>,
+}
+
+/// The inherited properties of an element.
+#[cfg(feature = "roxmltree")]
+#[derive(Copy, Clone)]
+struct Context<S> {
+    transform: Transform<S>,
+    filled: bool,
+    fill_rule: FillRule,
+}
+
+#[cfg(feature = "roxmltree")]
+struct Importer<'d, 'i, S> {
+    node: roxmltree::Node<'d, 'i>,
+    builder: ShapeBuilder<S>,
+    /// The filled elements with their fill rule.
+    elements: Vec<(Shape<S>, FillRule)>,
+}
+
+#[cfg(feature = "roxmltree")]
+impl<'d, 'i, S> Importer<'d, 'i, S>
+where
+    S: Real + RelativeEq + FloatConst + From<u16>,
+{
+    fn position(&self) -> (u32, u32) {
+        let pos = self.node.document().text_pos_at(self.node.range().start);
+        (pos.row, pos.col)
+    }
+
+    fn attribute_error(&self, name: &'static str) -> SvgError {
+        let (line, column) = self.position();
+        SvgError::Attribute { line, column, name }
+    }
+
+    /// Returns the value of the property `name` from the `style` attribute
+    /// or the presentation attribute.
+    fn property(&self, name: &str) -> Option<&'d str> {
+        let from_style = self.node.attribute("style").and_then(|style| {
+            style.split(';').find_map(|declaration| {
+                let mut parts = declaration.splitn(2, ':');
+                let key = parts.next()?.trim();
+                let value = parts.next()?.trim();
+                if key == name {
+                    Some(value.trim_end_matches("!important").trim_end())
+                } else {
+                    None
+                }
+            })
+        });
+        from_style.or_else(|| self.node.attribute(name).map(str::trim))
+    }
+
+    fn numbers(&self, name: &'static str) -> Result<Vec<S>, SvgError> {
+        let data = self.node.attribute(name).unwrap_or("");
+        let mut lexer = Lexer { data, pos: 0 };
+        let mut numbers = Vec::new();
+        while lexer.peek().is_some() {
+            numbers.push(lexer.number().map_err(|_| self.attribute_error(name))?);
+        }
+        Ok(numbers)
+    }
+
+    /// Returns the length `name` in user units. Only absolute units (like
+    /// `px`, `mm` or `pt`) are supported.
+    fn length(&self, name: &'static str) -> Result<Option<S>, SvgError> {
+        let value = match self.node.attribute(name) {
+            Some(value) => value.trim(),
+            None => return Ok(None),
+        };
+        let mut lexer = Lexer {
+            data: value,
+            pos: 0,
+        };
+        let length: S = lexer.number().map_err(|_| self.attribute_error(name))?;
+        match unit_scale::<S>(&value[lexer.pos..]) {
+            Some(scale) => Ok(Some(length * scale)),
+            None => Err(self.attribute_error(name)),
+        }
+    }
+
+    /// Returns `true`, if one of the lengths `names` is relative (like `50%`
+    /// or `2em`), which can't be resolved without the viewport or the font.
+    fn has_relative_length(&self, names: &[&str]) -> bool {
+        names
+            .iter()
+            .filter_map(|name| self.node.attribute(*name))
+            .any(|value| {
+                let value = value.trim_end();
+                RELATIVE_UNITS.iter().any(|unit| value.ends_with(unit))
+            })
+    }
+
+    fn transform(&self) -> Result<Transform<S>, SvgError> {
+        match self.node.attribute("transform") {
+            Some(data) => parse_transform(data).map_err(|error| {
+                let (line, column) = self.position();
+                SvgError::Transform {
+                    line,
+                    column,
+                    error,
+                }
+            }),
+            None => Ok(Transform::identity()),
+        }
+    }
+
+    fn context(&self, parent: &Context<S>) -> Result<Context<S>, SvgError> {
+        let mut context = *parent;
+        context.transform = parent.transform * self.transform()?;
+        match self.property("fill") {
+            Some("none") => context.filled = false,
+            Some("inherit") | None => (),
+            Some(_) => context.filled = true,
+        }
+        match self.property("fill-rule") {
+            Some("nonzero") => context.fill_rule = FillRule::NonZero,
+            Some("evenodd") => context.fill_rule = FillRule::EvenOdd,
+            Some("inherit") | None => (),
+            Some(_) => return Err(self.attribute_error("fill-rule")),
+        }
+        Ok(context)
+    }
+
+    fn import(
+        &mut self,
+        node: roxmltree::Node<'d, 'i>,
+        parent: &Context<S>,
+    ) -> Result<(), SvgError> {
+        self.node = node;
+        if !node.is_element() || self.property("display") == Some("none") {
+            return Ok(());
+        }
+        let context = self.context(parent)?;
+        match node.tag_name().name() {
+            "svg" | "g" | "a" => {
+                for child in node.children() {
+                    self.import(child, &context)?;
+                }
+            }
+            "path" | "rect" | "circle" | "ellipse" | "polygon" | "polyline" if context.filled => {
+                self.element(&context)?;
+                let builder = core::mem::take(&mut self.builder);
+                if let Some(shape) = builder.finish() {
+                    self.elements.push((shape, context.fill_rule));
+                }
+            }
+            // other elements (like `<defs>` or `<text>`) are ignored
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Adds the outline of the current shape element.
+    fn element(&mut self, context: &Context<S>) -> Result<(), SvgError> {
+        let _0 = S::zero();
+        let _2 = S::one() + S::one();
+        let node = self.node;
+        match node.tag_name().name() {
+            "path" => {
+                let data = node.attribute("d").unwrap_or("");
+                let mut sink = Transformed {
+                    builder: &mut self.builder,
+                    transform: context.transform,
+                };
+                if let Err(error) = build_path_into(data, &mut sink) {
+                    let (line, column) = self.position();
+                    return Err(SvgError::Path {
+                        line,
+                        column,
+                        error,
+                    });
+                }
+            }
+            "rect" => {
+                if self.has_relative_length(&["x", "y", "width", "height", "rx", "ry"]) {
+                    return Ok(());
+                }
+                let x = self.length("x")?.unwrap_or(_0);
+                let y = self.length("y")?.unwrap_or(_0);
+                let width = self.length("width")?.unwrap_or(_0);
+                let height = self.length("height")?.unwrap_or(_0);
+                if width <= _0 || height <= _0 {
+                    return Ok(());
+                }
+                // a missing radius equals the other one
+                let (rx, ry) = match (self.length("rx")?, self.length("ry")?) {
+                    (Some(rx), Some(ry)) => (rx, ry),
+                    (Some(r), None) | (None, Some(r)) => (r, r),
+                    (None, None) => (_0, _0),
+                };
+                let rx = rx.max(_0).min(width / _2);
+                let ry = ry.max(_0).min(height / _2);
+                let mut sink = Transformed {
+                    builder: &mut self.builder,
+                    transform: context.transform,
+                };
+                let radii = Point2::new(rx, ry);
+                let corner = |sink: &mut Transformed<S>, from: Point2<S>, to: Point2<S>| {
+                    arc_to(sink, from, radii, _0, (false, true), to)
+                };
+                let (x1, y1) = (x + width, y + height);
+                sink.move_to(Point2::new(x + rx, y));
+                sink.line_to(Point2::new(x1 - rx, y));
+                corner(&mut sink, Point2::new(x1 - rx, y), Point2::new(x1, y + ry));
+                sink.line_to(Point2::new(x1, y1 - ry));
+                corner(
+                    &mut sink,
+                    Point2::new(x1, y1 - ry),
+                    Point2::new(x1 - rx, y1),
+                );
+                sink.line_to(Point2::new(x + rx, y1));
+                corner(&mut sink, Point2::new(x + rx, y1), Point2::new(x, y1 - ry));
+                sink.line_to(Point2::new(x, y + ry));
+                corner(&mut sink, Point2::new(x, y + ry), Point2::new(x + rx, y));
+                sink.close();
+            }
+            "circle" | "ellipse" => {
+                if self.has_relative_length(&["cx", "cy", "r", "rx", "ry"]) {
+                    return Ok(());
+                }
+                let cx = self.length("cx")?.unwrap_or(_0);
+                let cy = self.length("cy")?.unwrap_or(_0);
+                let radii = if node.has_tag_name("circle") {
+                    let r = self.length("r")?.unwrap_or(_0);
+                    Point2::new(r, r)
+                } else {
+                    Point2::new(
+                        self.length("rx")?.unwrap_or(_0),
+                        self.length("ry")?.unwrap_or(_0),
+                    )
+                };
+                if radii.x <= _0 || radii.y <= _0 {
+                    return Ok(());
+                }
+                let mut sink = Transformed {
+                    builder: &mut self.builder,
+                    transform: context.transform,
+                };
+                let right = Point2::new(cx + radii.x, cy);
+                let left = Point2::new(cx - radii.x, cy);
+                sink.move_to(right);
+                arc_to(&mut sink, right, radii, _0, (false, true), left);
+                arc_to(&mut sink, left, radii, _0, (false, true), right);
+                sink.close();
+            }
+            _ => {
+                // polygon or polyline, which is closed for filling as well
+                let numbers = self.numbers("points")?;
+                if numbers.len() % 2 != 0 {
+                    return Err(self.attribute_error("points"));
+                }
+                let mut sink = Transformed {
+                    builder: &mut self.builder,
+                    transform: context.transform,
+                };
+                let mut points = numbers.chunks(2).map(|p| Point2::new(p[0], p[1]));
+                if let Some(first) = points.next() {
+                    sink.move_to(first);
+                    for point in points {
+                        sink.line_to(point);
+                    }
+                    sink.close();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The units of lengths, which are relative to the viewport or the font.
+#[cfg(feature = "roxmltree")]
+const RELATIVE_UNITS: &[&str] = &["%", "em", "ex", "ch", "rem", "vw", "vh", "vmin", "vmax"];
+
+/// Returns the size of the absolute length `unit` in user units (pixels).
+#[cfg(feature = "roxmltree")]
+fn unit_scale<S: Real + From<u16>>(unit: &str) -> Option<S> {
+    let (numerator, denominator): (u16, u16) = match unit {
+        "" | "px" => (1, 1),
+        "in" => (96, 1),
+        "cm" => (9600, 254),
+        "mm" => (960, 254),
+        "Q" => (240, 254),
+        "pt" => (4, 3),
+        "pc" => (16, 1),
+        _ => return None,
+    };
+    Some(<S as From<u16>>::from(numerator) / <S as From<u16>>::from(denominator))
+}
+
+/// Parses the value of a `transform` attribute.
+#[cfg(feature = "roxmltree")]
+fn parse_transform<S: Real>(data: &str) -> Result<Transform<S>, PathError> {
+    let mut lexer = Lexer { data, pos: 0 };
+    let mut transform = Transform::identity();
+    while lexer.peek().is_some() {
+        let start = lexer.pos;
+        let name_len = data[start..]
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(data.len() - start);
+        lexer.pos += name_len;
+        let name = &data[start..lexer.pos];
+        let known = ["matrix", "translate", "scale", "rotate", "skewX", "skewY"];
+        if !known.contains(&name) {
+            return Err(PathError {
+                kind: PathErrorKind::UnknownTransform,
+                offset: start,
+            });
+        }
+        if lexer.peek() != Some(b'(') {
+            return Err(lexer.error(PathErrorKind::ExpectedParenthesis));
+        }
+        lexer.pos += 1;
+        let mut args = [S::zero(); 6];
+        let mut count = 0;
+        while lexer.at_number() && count < args.len() {
+            args[count] = lexer.number()?;
+            count += 1;
+        }
+        match lexer.peek() {
+            Some(b')') => (),
+            // more arguments than any function takes
+            Some(_) if lexer.at_number() => {
+                return Err(PathError {
+                    kind: PathErrorKind::ArgumentCount,
+                    offset: start,
+                })
+            }
+            _ => return Err(lexer.error(PathErrorKind::ExpectedParenthesis)),
+        }
+        lexer.pos += 1;
+        let [a, b, c, d, e, f] = args;
+        let next = match (name, count) {
+            ("matrix", 6) => Transform::new(a, b, c, d, e, f),
+            ("translate", 1) => Transform::translation(a, S::zero()),
+            ("translate", 2) => Transform::translation(a, b),
+            ("scale", 1) => Transform::scaling(a, a),
+            ("scale", 2) => Transform::scaling(a, b),
+            ("rotate", 1) => Transform::rotation(a.to_radians()),
+            ("rotate", 3) => {
+                Transform::translation(b, c)
+                    * Transform::rotation(a.to_radians())
+                    * Transform::translation(-b, -c)
+            }
+            ("skewX", 1) => Transform::skew_x(a.to_radians()),
+            ("skewY", 1) => Transform::skew_y(a.to_radians()),
+            (_, _) => {
+                return Err(PathError {
+                    kind: PathErrorKind::ArgumentCount,
+                    offset: start,
+                })
+            }
+        };
+        transform = transform * next;
+    }
+    Ok(transform)
+}
+
+/// Imports the filled shapes of the SVG document `svg` into a single `Shape`.
+///
+/// Supports `<path>`, `<rect>`, `<circle>`, `<ellipse>`, `<polygon>` and
+/// `<polyline>` elements, nested in `<svg>`, `<g>` and `<a>` elements, with
+/// their `transform`, `fill`, `fill-rule` and `display` properties. Lengths
+/// in absolute units are converted into user units, while elements with
+/// relative lengths (like `%` or `em`) are skipped. Like in
+/// SVG, every element is filled by its own fill rule: several elements are
+/// combined into their union (see `Shape::union`), which has no overlapping
+/// contours. Strokes,
+/// `<use>` references and clip paths are ignored. Like path data, the result
+/// is in user units, with the y-axis pointing downwards.
+#[cfg(feature = "roxmltree")]
+pub fn parse_document<S>(svg: &str) -> Result<SvgShape<S>, SvgError>
+where
+    S: Real + RelativeEq + FloatConst + From<u16>,
+{
+    let document = roxmltree::Document::parse(svg).map_err(SvgError::Xml)?;
+    let root = document.root_element();
+    let mut importer = Importer {
+        node: root,
+        builder: Shape::builder(),
+        elements: Vec::new(),
+    };
+    let view_box = match importer.numbers("viewBox")?.as_slice() {
+        [x, y, width, height] => Some(Aabr {
+            min: Point2::new(*x, *y),
+            max: Point2::new(*x + *width, *y + *height),
+        }),
+        // relative sizes (like `100%`) are ignored
+        [] => match (
+            importer.length("width").ok().flatten(),
+            importer.length("height").ok().flatten(),
+        ) {
+            (Some(width), Some(height)) => Some(Aabr {
+                min: Point2::zero(),
+                max: Point2::new(width, height),
+            }),
+            _ => None,
+        },
+        _ => return Err(importer.attribute_error("viewBox")),
+    };
+    let context = Context {
+        transform: Transform::identity(),
+        filled: true,
+        fill_rule: FillRule::NonZero,
+    };
+    importer.import(root, &context)?;
+    let mut elements = importer.elements;
+    let (shape, fill_rule) = if elements.len() == 1 {
+        elements.pop().unwrap()
+    } else {
+        // every element is filled on its own, so they can't be flattened
+        // into one shape with a single fill rule
+        let operands: Vec<(&Shape<S>, FillRule)> = elements
+            .iter()
+            .map(|(shape, fill_rule)| (shape, *fill_rule))
+            .collect();
+        let shape = union_all(&operands).ok_or(SvgError::Empty)?;
+        (shape, FillRule::NonZero)
+    };
+    Ok(SvgShape {
+        shape,
+        fill_rule,
+        view_box,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    type P = Point2<f64>;
+
+    #[derive(Debug, PartialEq)]
+    enum Command {
+        Move(P),
+        Line(P),
+        Quadratic(P, P),
+        Cubic(P, P, P),
+        Close,
+    }
+
+    impl PathSink<f64> for Vec<Command> {
+        fn move_to(&mut self, to: P) {
+            self.push(Command::Move(to));
+        }
+        fn line_to(&mut self, to: P) {
+            self.push(Command::Line(to));
+        }
+        fn quadratic_to(&mut self, ctrl: P, to: P) {
+            self.push(Command::Quadratic(ctrl, to));
+        }
+        fn cubic_to(&mut self, ctrl0: P, ctrl1: P, to: P) {
+            self.push(Command::Cubic(ctrl0, ctrl1, to));
+        }
+        fn close(&mut self) {
+            self.push(Command::Close);
+        }
+    }
+
+    fn p(x: f64, y: f64) -> P {
+        Point2::new(x, y)
+    }
+
+    fn commands(data: &str) -> Vec<Command> {
+        let mut commands = Vec::new();
+        build_path_into(data, &mut commands).unwrap();
+        commands
+    }
+
+    fn error(data: &str) -> PathError {
+        parse_path::<f64>(data).err().unwrap()
+    }
+
+    #[test]
+    fn horizontal_and_vertical_lines() {
+        use Command::*;
+        assert_eq!(
+            commands("M1 2 H5 V7 h-1 v-2 z"),
+            [
+                Move(p(1.0, 2.0)),
+                Line(p(5.0, 2.0)),
+                Line(p(5.0, 7.0)),
+                Line(p(4.0, 7.0)),
+                Line(p(4.0, 5.0)),
+                Close,
+            ]
+        );
+    }
+
+    #[test]
+    fn implicit_commands_and_numbers() {
+        use Command::*;
+        assert_eq!(
+            commands("m1e1-2.5.5.5l1,1 2-2"),
+            [
+                Move(p(10.0, -2.5)),
+                Line(p(10.5, -2.0)),
+                Line(p(11.5, -1.0)),
+                Line(p(13.5, -3.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn smooth_cubic_reflects_the_previous_control_point() {
+        use Command::*;
+        assert_eq!(
+            commands("M0 0 C1 1 2 1 3 0 S5 -1 6 0 s1 1 2 0"),
+            [
+                Move(p(0.0, 0.0)),
+                Cubic(p(1.0, 1.0), p(2.0, 1.0), p(3.0, 0.0)),
+                Cubic(p(4.0, -1.0), p(5.0, -1.0), p(6.0, 0.0)),
+                Cubic(p(7.0, 1.0), p(7.0, 1.0), p(8.0, 0.0)),
+            ]
+        );
+        // without a previous cubic, the control point is the current point
+        assert_eq!(
+            commands("M0 0 L1 0 S2 1 3 0")[2],
+            Cubic(p(1.0, 0.0), p(2.0, 1.0), p(3.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn smooth_quadratic_reflects_the_previous_control_point() {
+        use Command::*;
+        assert_eq!(
+            commands("M0 0 Q1 2 2 0 T4 0 t2 0"),
+            [
+                Move(p(0.0, 0.0)),
+                Quadratic(p(1.0, 2.0), p(2.0, 0.0)),
+                Quadratic(p(3.0, -2.0), p(4.0, 0.0)),
+                Quadratic(p(5.0, 2.0), p(6.0, 0.0)),
+            ]
+        );
+        assert_eq!(
+            commands("M0 0 C1 1 2 1 3 0 T4 0")[2],
+            Quadratic(p(3.0, 0.0), p(4.0, 0.0))
+        );
+    }
+
+    /// Returns points on the cubic segments, which approximate an arc.
+    fn arc_points(data: &str) -> Vec<P> {
+        let commands = commands(data);
+        let mut start = match commands[0] {
+            Command::Move(start) => start,
+            ref command => panic!("unexpected {:?}", command),
+        };
+        let mut points = Vec::new();
+        for command in &commands[1..] {
+            match *command {
+                Command::Cubic(ctrl0, ctrl1, end) => {
+                    let cubic = crate::shape::CubicBezier2 {
+                        start,
+                        ctrl0,
+                        ctrl1,
+                        end,
+                    };
+                    points.extend((1..=4).map(|i| cubic.evaluate(i as f64 / 4.0)));
+                    start = end;
+                }
+                ref command => panic!("unexpected {:?}", command),
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn arcs() {
+        // a half circle around (1, 0), clockwise on screen with the sweep flag
+        let points = arc_points("M0 0 A1 1 0 0 1 2 0");
+        assert_eq!(*points.last().unwrap(), p(2.0, 0.0));
+        let middle = points[points.len() / 2 - 1];
+        assert!((middle - p(1.0, -1.0)).magnitude() < 1e-3, "{:?}", middle);
+        let points = arc_points("M0 0 A1 1 0 0 0 2 0");
+        let middle = points[points.len() / 2 - 1];
+        assert!((middle - p(1.0, 1.0)).magnitude() < 1e-3, "{:?}", middle);
+
+        // the large arc of a circle with radius 2 reaches below y = 2, the
+        // small one stays above
+        let center = p(1.0, 3f64.sqrt());
+        for (data, large) in &[
+            ("M0 0 A2 2 0 1 0 2 0", true),
+            ("M0 0 A2 2 0 0 0 2 0", false),
+        ] {
+            let points = arc_points(data);
+            let max_y = points.iter().map(|p| p.y).fold(f64::MIN, f64::max);
+            assert_eq!(max_y > 2.0, *large, "{}", data);
+            for point in &points[3..points.len() - 1] {
+                let radius = (*point - center).magnitude();
+                assert!((radius - 2.0).abs() < 1e-2, "{}: {}", data, radius);
+            }
+        }
+    }
+
+    #[test]
+    fn arc_flags_need_no_separators() {
+        assert_eq!(
+            commands("M0 0 A1 1 0 012 0"),
+            commands("M0 0 A1 1 0 0 1 2 0")
+        );
+    }
+
+    #[test]
+    fn degenerate_arcs() {
+        use Command::*;
+        // zero radius gives a line, equal end points nothing
+        assert_eq!(
+            commands("M0 0 A0 1 0 0 1 2 0 A1 1 0 0 1 2 0"),
+            [Move(p(0.0, 0.0)), Line(p(2.0, 0.0))]
+        );
+        // too small radii are scaled up to a half circle
+        let points = arc_points("M0 0 A0.5 0.5 0 0 1 2 0");
+        let middle = points[points.len() / 2 - 1];
+        assert!((middle - p(1.0, -1.0)).magnitude() < 1e-3, "{:?}", middle);
+    }
+
+    #[test]
+    fn error_offsets() {
+        let check = |data: &str, kind: PathErrorKind, offset: usize| {
+            assert_eq!(error(data), PathError { kind, offset }, "{}", data);
+        };
+        check("L1 2", PathErrorKind::ExpectedMove, 0);
+        check("  l1 2", PathErrorKind::ExpectedMove, 2);
+        check("M0 0 X1 2", PathErrorKind::UnknownCommand('X'), 5);
+        check("M0 0 L1", PathErrorKind::ExpectedNumber, 7);
+        check("M0 0 L1 -", PathErrorKind::ExpectedNumber, 8);
+        check("M0 0 A1 1 0 2 1 2 0", PathErrorKind::ExpectedFlag, 12);
+        check("M0 0 A1 1 0 0", PathErrorKind::ExpectedFlag, 13);
+        check("M0 0", PathErrorKind::Empty, 4);
+        check("", PathErrorKind::Empty, 0);
+    }
+
+    #[cfg(feature = "roxmltree")]
+    fn is_filled(svg: &SvgShape<f64>, x: f64, y: f64) -> bool {
+        svg.shape.scanline(y).is_filled(x, svg.fill_rule)
+    }
+
+    #[cfg(feature = "roxmltree")]
+    #[test]
+    fn overlapping_even_odd_elements() {
+        let svg = parse_document::<f64>(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" fill-rule="evenodd">
+                <rect x="0" y="0" width="20" height="20"/>
+                <rect x="10" y="10" width="20" height="20"/>
+            </svg>"#,
+        )
+        .unwrap();
+        assert!(is_filled(&svg, 5.0, 5.0));
+        assert!(is_filled(&svg, 15.0, 15.0));
+        assert!(is_filled(&svg, 25.0, 25.0));
+        assert!(!is_filled(&svg, 25.0, 5.0));
+    }
+
+    #[cfg(feature = "roxmltree")]
+    #[test]
+    fn overlapping_elements_with_opposite_directions() {
+        let svg = parse_document::<f64>(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <path d="M0 0 H20 V20 H0 Z"/>
+                <path d="M10 10 V30 H30 V10 Z"/>
+            </svg>"#,
+        )
+        .unwrap();
+        assert_eq!(svg.fill_rule, FillRule::NonZero);
+        assert!(is_filled(&svg, 5.0, 5.0));
+        assert!(is_filled(&svg, 15.0, 15.0));
+        assert!(is_filled(&svg, 25.0, 25.0));
+    }
+
+    #[cfg(feature = "roxmltree")]
+    #[test]
+    fn fill_rules_of_elements() {
+        // a single element keeps its fill rule
+        let svg = parse_document::<f64>(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <path fill-rule="evenodd" d="M0 0 H30 V30 H0 Z M10 10 H20 V20 H10 Z"/>
+            </svg>"#,
+        )
+        .unwrap();
+        assert_eq!(svg.fill_rule, FillRule::EvenOdd);
+        assert!(!is_filled(&svg, 15.0, 15.0));
+
+        // the hole of an even-odd element stays empty next to another element
+        let svg = parse_document::<f64>(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <path fill-rule="evenodd" d="M0 0 H30 V30 H0 Z M10 10 H20 V20 H10 Z"/>
+                <circle cx="50" cy="15" r="10"/>
+            </svg>"#,
+        )
+        .unwrap();
+        assert!(is_filled(&svg, 5.0, 15.0));
+        assert!(!is_filled(&svg, 15.0, 15.0));
+        assert!(is_filled(&svg, 50.0, 15.0));
+        assert!(!is_filled(&svg, 35.0, 15.0));
+    }
+
+    #[cfg(feature = "roxmltree")]
+    fn transformed(data: &str, x: f64, y: f64) -> P {
+        parse_transform::<f64>(data)
+            .unwrap()
+            .transform_point(p(x, y))
+    }
+
+    #[cfg(feature = "roxmltree")]
+    #[test]
+    fn transforms() {
+        let check = |data: &str, expected: P| {
+            let point = transformed(data, 1.0, 2.0);
+            assert!(point.distance(expected) < 1e-9, "{}: {:?}", data, point);
+        };
+        check("translate(10)", p(11.0, 2.0));
+        check("translate(10, -5)", p(11.0, -3.0));
+        check("scale(2)", p(2.0, 4.0));
+        check("scale(2 3)", p(2.0, 6.0));
+        check("rotate(90)", p(-2.0, 1.0));
+        check("rotate(90 1 1)", p(0.0, 1.0));
+        check("rotate(-90, 1, 2)", p(1.0, 2.0));
+        check("matrix(1 2 3 4 5 6)", p(12.0, 16.0));
+        check("skewX(45)", p(3.0, 2.0));
+        check("skewY(45)", p(1.0, 3.0));
+        // the last transform is applied first
+        check("translate(10 0) scale(2)", p(12.0, 4.0));
+        check(" scale(2),translate(10 0) ", p(22.0, 4.0));
+        check("", p(1.0, 2.0));
+    }
+
+    #[cfg(feature = "roxmltree")]
+    #[test]
+    fn transform_errors() {
+        let check = |data: &str, kind: PathErrorKind, offset: usize| {
+            let error = parse_transform::<f64>(data).unwrap_err();
+            assert_eq!(error, PathError { kind, offset }, "{}", data);
+        };
+        check("foo(1)", PathErrorKind::UnknownTransform, 0);
+        check("scale(1) 2", PathErrorKind::UnknownTransform, 9);
+        check("translate 1", PathErrorKind::ExpectedParenthesis, 10);
+        check("translate(1", PathErrorKind::ExpectedParenthesis, 11);
+        check("translate(1, x)", PathErrorKind::ExpectedParenthesis, 13);
+        check("scale(1 2 3)", PathErrorKind::ArgumentCount, 0);
+        check("matrix(1 2 3 4 5 6 7)", PathErrorKind::ArgumentCount, 0);
+        check("scale(1) rotate()", PathErrorKind::ArgumentCount, 9);
+    }
+
+    #[cfg(feature = "roxmltree")]
+    fn bounds(svg: &str) -> Aabr<f64> {
+        parse_document::<f64>(svg).unwrap().shape.aabr()
+    }
+
+    #[cfg(feature = "roxmltree")]
+    fn assert_bounds(svg: &str, min: P, max: P) {
+        let aabr = bounds(svg);
+        let close = aabr.min.distance(min) < 1e-9 && aabr.max.distance(max) < 1e-9;
+        assert!(close, "{:?}", aabr);
+    }
+
+    #[cfg(feature = "roxmltree")]
+    #[test]
+    fn nested_group_transforms() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <g transform="translate(100 0)">
+                <g transform="scale(2)">
+                    <rect x="1" y="2" width="3" height="4" transform="translate(0 1)"/>
+                </g>
+            </g>
+        </svg>"#;
+        assert_bounds(svg, p(102.0, 6.0), p(108.0, 14.0));
+        let svg = parse_document::<f64>(svg).unwrap();
+        assert!(is_filled(&svg, 105.0, 10.0));
+        assert!(!is_filled(&svg, 3.0, 4.0));
+    }
+
+    #[cfg(feature = "roxmltree")]
+    #[test]
+    fn invalid_transforms() {
+        let error = parse_document::<f64>(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <rect width="1" height="1" transform="scale(1 2 3)"/>
+            </svg>"#,
+        )
+        .unwrap_err();
+        match error {
+            SvgError::Transform { line, error, .. } => {
+                assert_eq!(line, 2);
+                assert_eq!(error.kind, PathErrorKind::ArgumentCount);
+            }
+            error => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[cfg(feature = "roxmltree")]
+    #[test]
+    fn absolute_units() {
+        assert_bounds(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <rect x="1in" y="6pc" width="72pt" height="2.54cm"/>
+            </svg>"#,
+            p(96.0, 96.0),
+            p(192.0, 192.0),
+        );
+        assert_bounds(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <circle cx="10px" cy="25.4mm" r="7.5pt"/>
+            </svg>"#,
+            p(0.0, 86.0),
+            p(20.0, 106.0),
+        );
+        assert_bounds(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <rect width="101.6Q" height="0.5in"/>
+            </svg>"#,
+            p(0.0, 0.0),
+            p(96.0, 48.0),
+        );
+    }
+
+    #[cfg(feature = "roxmltree")]
+    #[test]
+    fn relative_units() {
+        // elements with relative lengths are skipped
+        assert_bounds(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100%" height="100%">
+                <rect width="50%" height="10"/>
+                <rect x="2em" width="10" height="10"/>
+                <circle r="1vmin"/>
+                <rect x="5" y="5" width="10" height="10"/>
+            </svg>"#,
+            p(5.0, 5.0),
+            p(15.0, 15.0),
+        );
+        let error = parse_document::<f64>(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <rect width="10 px" height="10"/>
+            </svg>"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            SvgError::Attribute {
+                line: 2,
+                column: 17,
+                name: "width"
+            }
+        );
+    }
+}
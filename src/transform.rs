@@ -0,0 +1,141 @@
+//! Affine transformations of the plane.
+
+use core::ops::Mul;
+use num_traits::real::Real;
+
+use crate::shape::{Point2, Vec2};
+
+/// An affine transformation, which maps `(x, y)` to
+/// `(a * x + c * y + e, b * x + d * y + f)`.
+///
+/// This is the matrix `[a c e; b d f; 0 0 1]`, in the notation of SVG.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform<S> {
+    pub a: S,
+    pub b: S,
+    pub c: S,
+    pub d: S,
+    pub e: S,
+    pub f: S,
+}
+
+impl<S: Real> Default for Transform<S> {
+    #[inline]
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl<S: Real> Transform<S> {
+    #[inline]
+    pub fn new(a: S, b: S, c: S, d: S, e: S, f: S) -> Self {
+        Transform { a, b, c, d, e, f }
+    }
+
+    #[inline]
+    pub fn identity() -> Self {
+        Self::scaling(S::one(), S::one())
+    }
+
+    #[inline]
+    pub fn translation(x: S, y: S) -> Self {
+        Self::new(S::one(), S::zero(), S::zero(), S::one(), x, y)
+    }
+
+    #[inline]
+    pub fn scaling(x: S, y: S) -> Self {
+        Self::new(x, S::zero(), S::zero(), y, S::zero(), S::zero())
+    }
+
+    /// Rotates by `angle` (in radians) from the x-axis towards the y-axis.
+    #[inline]
+    pub fn rotation(angle: S) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin, -sin, cos, S::zero(), S::zero())
+    }
+
+    /// Skews along the x-axis by `angle` (in radians).
+    #[inline]
+    pub fn skew_x(angle: S) -> Self {
+        Self::new(
+            S::one(),
+            S::zero(),
+            angle.tan(),
+            S::one(),
+            S::zero(),
+            S::zero(),
+        )
+    }
+
+    /// Skews along the y-axis by `angle` (in radians).
+    #[inline]
+    pub fn skew_y(angle: S) -> Self {
+        Self::new(
+            S::one(),
+            angle.tan(),
+            S::zero(),
+            S::one(),
+            S::zero(),
+            S::zero(),
+        )
+    }
+
+    /// Returns the transformation, which applies `self` and then `next`.
+    #[inline]
+    pub fn then(self, next: Self) -> Self {
+        next * self
+    }
+
+    #[inline]
+    pub fn determinant(&self) -> S {
+        self.a * self.d - self.b * self.c
+    }
+
+    /// Returns the inverse transformation, if `self` is invertible.
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det.abs() < S::min_positive_value() {
+            return None;
+        }
+        let (a, b, c, d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+        Some(Self::new(
+            a,
+            b,
+            c,
+            d,
+            -(a * self.e + c * self.f),
+            -(b * self.e + d * self.f),
+        ))
+    }
+
+    #[inline]
+    pub fn transform_point(&self, p: Point2<S>) -> Point2<S> {
+        Point2::new(
+            self.a * p.x + self.c * p.y + self.e,
+            self.b * p.x + self.d * p.y + self.f,
+        )
+    }
+
+    /// Transforms a direction, which ignores the translation.
+    #[inline]
+    pub fn transform_vector(&self, v: Vec2<S>) -> Vec2<S> {
+        Vec2::new(self.a * v.x + self.c * v.y, self.b * v.x + self.d * v.y)
+    }
+}
+
+/// The product of the matrices: `rhs` is applied first.
+impl<S: Real> Mul for Transform<S> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.a * rhs.a + self.c * rhs.b,
+            self.b * rhs.a + self.d * rhs.b,
+            self.a * rhs.c + self.c * rhs.d,
+            self.b * rhs.c + self.d * rhs.d,
+            self.a * rhs.e + self.c * rhs.f + self.e,
+            self.b * rhs.e + self.d * rhs.f + self.f,
+        )
+    }
+}